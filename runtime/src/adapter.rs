@@ -0,0 +1,165 @@
+//! `Arbitrary` impls for standard library types, which `arbitrary` 1.x lacks in some or all of
+//! its versions. Generated code decodes such arguments as `Adapter<T>` and passes on the inner
+//! value, so the types do not need newtypes in the user's crate.
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use std::cmp::Ordering;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::num::{
+    NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
+    NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize,
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Wrapper, which implements `Arbitrary` for a foreign type `T`.
+pub struct Adapter<T>(pub T);
+
+impl<'a> Arbitrary<'a> for Adapter<Ipv4Addr> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Adapter(Ipv4Addr::from(u32::arbitrary(u)?)))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Adapter<Ipv6Addr> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Adapter(Ipv6Addr::from(u128::arbitrary(u)?)))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Adapter<IpAddr> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Adapter(if bool::arbitrary(u)? {
+            IpAddr::V4(Adapter::arbitrary(u)?.0)
+        } else {
+            IpAddr::V6(Adapter::arbitrary(u)?.0)
+        }))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Adapter<SocketAddrV4> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let ip = Adapter::arbitrary(u)?.0;
+        Ok(Adapter(SocketAddrV4::new(ip, u16::arbitrary(u)?)))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Adapter<SocketAddrV6> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let ip = Adapter::arbitrary(u)?.0;
+        Ok(Adapter(SocketAddrV6::new(
+            ip,
+            u16::arbitrary(u)?,
+            u32::arbitrary(u)?,
+            u32::arbitrary(u)?,
+        )))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Adapter<SocketAddr> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Adapter(if bool::arbitrary(u)? {
+            SocketAddr::V4(Adapter::arbitrary(u)?.0)
+        } else {
+            SocketAddr::V6(Adapter::arbitrary(u)?.0)
+        }))
+    }
+}
+
+macro_rules! impl_adapter_for_non_zero {
+    ($($non_zero:ident($int:ty))*) => {
+        $(
+            impl<'a> Arbitrary<'a> for Adapter<$non_zero> {
+                fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+                    let value = <$int>::arbitrary(u)?;
+                    // Zero is mapped to one, so every input decodes to some value
+                    Ok(Adapter($non_zero::new(value).or_else(|| $non_zero::new(1)).unwrap()))
+                }
+            }
+        )*
+    };
+}
+
+impl_adapter_for_non_zero! {
+    NonZeroU8(u8) NonZeroU16(u16) NonZeroU32(u32) NonZeroU64(u64) NonZeroU128(u128)
+    NonZeroUsize(usize)
+    NonZeroI8(i8) NonZeroI16(i16) NonZeroI32(i32) NonZeroI64(i64) NonZeroI128(i128)
+    NonZeroIsize(isize)
+}
+
+impl<'a> Arbitrary<'a> for Adapter<SystemTime> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let before_epoch = bool::arbitrary(u)?;
+        let offset = Duration::arbitrary(u)?;
+        // Times, which the platform can not represent, are clamped to the epoch
+        let time = if before_epoch {
+            UNIX_EPOCH.checked_sub(offset)
+        } else {
+            UNIX_EPOCH.checked_add(offset)
+        };
+        Ok(Adapter(time.unwrap_or(UNIX_EPOCH)))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Adapter<Ordering> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Adapter(match u8::arbitrary(u)? % 3 {
+            0 => Ordering::Less,
+            1 => Ordering::Equal,
+            _ => Ordering::Greater,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode<T>(data: &[u8]) -> T
+    where
+        Adapter<T>: for<'a> Arbitrary<'a>,
+    {
+        Adapter::<T>::arbitrary(&mut Unstructured::new(data))
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn net() {
+        assert_eq!(
+            decode::<Ipv4Addr>(&[1, 0, 0, 127]),
+            Ipv4Addr::new(127, 0, 0, 1)
+        );
+        assert_eq!(
+            decode::<IpAddr>(&[1, 1, 0, 0, 127]),
+            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))
+        );
+        let mut data = vec![0];
+        data.extend_from_slice(&1u128.to_le_bytes());
+        data.extend_from_slice(&8080u16.to_le_bytes());
+        assert_eq!(
+            decode::<SocketAddr>(&data),
+            SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::LOCALHOST, 8080, 0, 0))
+        );
+    }
+
+    #[test]
+    fn non_zero() {
+        assert_eq!(decode::<NonZeroU32>(&[7, 0, 0, 0]).get(), 7);
+        assert_eq!(decode::<NonZeroU32>(&[0; 4]).get(), 1);
+        assert_eq!(decode::<NonZeroI8>(&[0xff]).get(), -1);
+    }
+
+    #[test]
+    fn system_time() {
+        assert_eq!(decode::<SystemTime>(&[]), UNIX_EPOCH);
+        let time = decode::<SystemTime>(&[0, 10, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(time.duration_since(UNIX_EPOCH).unwrap().as_secs(), 10);
+    }
+
+    #[test]
+    fn ordering() {
+        assert_eq!(decode::<Ordering>(&[]), Ordering::Less);
+        assert_eq!(decode::<Ordering>(&[4]), Ordering::Equal);
+        assert_eq!(decode::<Ordering>(&[2]), Ordering::Greater);
+    }
+}
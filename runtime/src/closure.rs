@@ -0,0 +1,121 @@
+//! Return values of closures, which generated code passes to fuzzed higher-order functions.
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Upper bound of decoded return values.
+const MAX_VALUES: usize = 16;
+
+/// Return values of a synthesized closure, which are picked by a hash of its arguments. The
+/// closure answers the same arguments the same way, so callees, which call it repeatedly, like
+/// `dedup_by` or a cache, see a consistent function. Arguments, which can not be hashed, get the
+/// values in turn.
+pub struct ReturnTable<R> {
+    values: Vec<R>,
+    calls: AtomicUsize,
+}
+
+impl<R: Clone> ReturnTable<R> {
+    /// Returns the value for a key of arguments, which `Args` computes, or the next value in turn
+    /// without a key.
+    pub fn answer(&self, key: Option<u64>) -> R {
+        let key = key.unwrap_or_else(|| self.calls.fetch_add(1, Ordering::Relaxed) as u64);
+        let index = (key % self.values.len() as u64) as usize;
+        self.values[index].clone()
+    }
+}
+
+/// Wrapper, which lets generated code hash arguments only if their types implement `Hash`:
+/// `(&Args(&args)).key()` resolves to `HashArgs` if they do, and to `OpaqueArgs` otherwise.
+pub struct Args<'a, T: ?Sized>(pub &'a T);
+
+pub trait HashArgs {
+    fn key(&self) -> Option<u64>;
+}
+
+impl<T: Hash + ?Sized> HashArgs for Args<'_, T> {
+    /// `DefaultHasher::new` has fixed keys, so answers do not change between runs, and crashes
+    /// reproduce.
+    fn key(&self) -> Option<u64> {
+        let mut hasher = DefaultHasher::new();
+        self.0.hash(&mut hasher);
+        Some(hasher.finish())
+    }
+}
+
+pub trait OpaqueArgs {
+    fn key(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl<T: ?Sized> OpaqueArgs for &Args<'_, T> {}
+
+impl<R> ReturnTable<R> {
+    /// Decodes return values with `decode`, for types, which are decoded through an adapter.
+    pub fn arbitrary_with<'a>(
+        u: &mut Unstructured<'a>,
+        mut decode: impl FnMut(&mut Unstructured<'a>) -> Result<R>,
+    ) -> Result<Self> {
+        let len = u.int_in_range(1..=MAX_VALUES)?;
+        let values = (0..len).map(|_| decode(u)).collect::<Result<Vec<_>>>()?;
+        Ok(ReturnTable {
+            values,
+            calls: AtomicUsize::new(0),
+        })
+    }
+}
+
+impl<'a, R: Arbitrary<'a>> Arbitrary<'a> for ReturnTable<R> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        ReturnTable::arbitrary_with(u, R::arbitrary)
+    }
+}
+
+impl<R: fmt::Debug> fmt::Debug for ReturnTable<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(&self.values).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // Borrows are needed by generated code, which does not know argument types
+    #[allow(clippy::needless_borrow)]
+    fn answers() {
+        let table = ReturnTable::<bool>::arbitrary(&mut Unstructured::new(&[2, 1, 0, 1])).unwrap();
+        assert_eq!(format!("{:?}", table), "[true, false, true]");
+        let answers: Vec<bool> = (0..32u8)
+            .map(|arg| table.answer((&Args(&(&arg,))).key()))
+            .collect();
+        assert!(answers.contains(&true) && answers.contains(&false));
+        for arg in 0..32u8 {
+            assert_eq!(table.answer((&Args(&(&arg,))).key()), answers[arg as usize]);
+        }
+
+        // Floats are not `Hash`, so they get the values in turn
+        let answers: Vec<bool> = [0.5f64, 0.5, 0.5, 0.5]
+            .iter()
+            .map(|arg| table.answer((&Args(&(arg,))).key()))
+            .collect();
+        assert_eq!(answers, [true, false, true, true]);
+
+        let table = ReturnTable::arbitrary_with(&mut Unstructured::new(&[1, 3, 4]), |u| {
+            Ok(u8::arbitrary(u)? % 2 == 0)
+        })
+        .unwrap();
+        assert_eq!(format!("{:?}", table), "[false, true]");
+
+        let table = ReturnTable::<u8>::arbitrary(&mut Unstructured::new(&[])).unwrap();
+        assert_eq!(
+            table.answer((&Args(&())).key()),
+            table.answer((&Args(&())).key())
+        );
+    }
+}
@@ -0,0 +1,421 @@
+//! Structure-aware string inputs. Fuzzed input bytes pick alternatives of a context-free grammar,
+//! so generated strings get past tokenizers, which reject nearly all random strings.
+//!
+//! Grammars are written one rule per line, the first rule is the start one:
+//! ```text
+//! # comment
+//! list ::= "[" items "]" | "[]"
+//!     | "null"
+//! items ::= item | item "," items
+//! item ::= [a-z0-9] | "\u{1F600}"
+//! ```
+//! Alternatives are separated by `|` and may continue on lines starting with `|`. Terminals are
+//! either string literals or character classes, which accept `\n`, `\t`, `\r` and `\u{...}`
+//! escapes, any other escaped character stands for itself.
+
+use arbitrary::{Result, Unstructured};
+use std::iter::Peekable;
+use std::str::Chars;
+use std::sync::OnceLock;
+
+/// Deeper rules only take their shortest alternative, so recursive grammars terminate.
+const MAX_DEPTH: usize = 32;
+/// Output length, after which rules only take their shortest alternative.
+const MAX_LEN: usize = 4096;
+
+/// JSON documents.
+pub static JSON: LazyGrammar = LazyGrammar::new(include_str!("../grammars/json.gram"));
+/// Absolute URLs.
+pub static URL: LazyGrammar = LazyGrammar::new(include_str!("../grammars/url.gram"));
+/// Statements of a small SQL dialect.
+pub static SQL: LazyGrammar = LazyGrammar::new(include_str!("../grammars/sql.gram"));
+
+/// Grammar, which is parsed on the first use, so generated code can keep it in a `static`.
+pub struct LazyGrammar {
+    source: &'static str,
+    grammar: OnceLock<Grammar>,
+}
+
+impl LazyGrammar {
+    pub const fn new(source: &'static str) -> LazyGrammar {
+        LazyGrammar {
+            source,
+            grammar: OnceLock::new(),
+        }
+    }
+
+    pub fn generate(&self, u: &mut Unstructured) -> Result<String> {
+        self.grammar
+            .get_or_init(|| {
+                Grammar::parse(self.source)
+                    .unwrap_or_else(|error| panic!("Invalid grammar: {}", error))
+            })
+            .generate(u)
+    }
+}
+
+#[derive(Debug)]
+pub struct Grammar {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug)]
+struct Rule {
+    alternatives: Vec<Vec<Symbol>>,
+    /// Alternative with the lowest derivation depth.
+    shortest: usize,
+}
+
+#[derive(Debug, PartialEq)]
+enum Symbol {
+    Text(String),
+    /// Inclusive character ranges.
+    Class(Vec<(char, char)>),
+    Rule(usize),
+}
+
+/// Symbol before rule names are resolved.
+enum Token {
+    Symbol(Symbol),
+    Name(String),
+}
+
+impl Grammar {
+    pub fn parse(source: &str) -> std::result::Result<Grammar, String> {
+        let mut names: Vec<String> = Vec::new();
+        let mut parsed: Vec<Vec<Vec<Token>>> = Vec::new();
+        for (number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |error: String| format!("line {}: {}", number + 1, error);
+            if let Some(rest) = line.strip_prefix('|') {
+                let alternatives = parsed
+                    .last_mut()
+                    .ok_or_else(|| error("alternatives before the first rule".to_string()))?;
+                alternatives.extend(parse_alternatives(rest).map_err(error)?);
+            } else {
+                let (name, rest) = line
+                    .split_once("::=")
+                    .ok_or_else(|| error("expected `name ::= alternatives`".to_string()))?;
+                let name = name.trim();
+                if !is_name(name) {
+                    return Err(error(format!("invalid rule name `{}`", name)));
+                }
+                if names.iter().any(|defined| defined == name) {
+                    return Err(error(format!("rule `{}` is defined twice", name)));
+                }
+                names.push(name.to_string());
+                parsed.push(parse_alternatives(rest).map_err(error)?);
+            }
+        }
+        if parsed.is_empty() {
+            return Err("grammar has no rules".to_string());
+        }
+
+        let mut rules = Vec::new();
+        for alternatives in parsed {
+            let mut resolved = Vec::new();
+            for alternative in alternatives {
+                let mut symbols = Vec::new();
+                for token in alternative {
+                    symbols.push(match token {
+                        Token::Symbol(symbol) => symbol,
+                        Token::Name(name) => Symbol::Rule(
+                            names
+                                .iter()
+                                .position(|defined| *defined == name)
+                                .ok_or_else(|| format!("rule `{}` is not defined", name))?,
+                        ),
+                    });
+                }
+                resolved.push(symbols);
+            }
+            rules.push(Rule {
+                alternatives: resolved,
+                shortest: 0,
+            });
+        }
+
+        // Derivation depths are found by a fixpoint, rules without one never terminate
+        let mut depths: Vec<Option<usize>> = vec![None; rules.len()];
+        loop {
+            let mut changed = false;
+            for (index, rule) in rules.iter_mut().enumerate() {
+                for (alternative_index, alternative) in rule.alternatives.iter().enumerate() {
+                    let depth = alternative
+                        .iter()
+                        .try_fold(0, |depth, symbol| match symbol {
+                            Symbol::Rule(rule) => {
+                                depths[*rule].map(|rule_depth| depth.max(rule_depth))
+                            }
+                            _ => Some(depth),
+                        });
+                    if let Some(depth) = depth.map(|depth| depth + 1) {
+                        if depths[index].map_or(true, |known| depth < known) {
+                            depths[index] = Some(depth);
+                            rule.shortest = alternative_index;
+                            changed = true;
+                        }
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        if let Some(index) = depths.iter().position(Option::is_none) {
+            return Err(format!("rule `{}` never terminates", names[index]));
+        }
+
+        Ok(Grammar { rules })
+    }
+
+    pub fn generate(&self, u: &mut Unstructured) -> Result<String> {
+        let mut output = String::new();
+        self.expand(0, u, 0, &mut output)?;
+        Ok(output)
+    }
+
+    fn expand(
+        &self,
+        rule: usize,
+        u: &mut Unstructured,
+        depth: usize,
+        output: &mut String,
+    ) -> Result<()> {
+        let rule = &self.rules[rule];
+        let exhausted = depth >= MAX_DEPTH || output.len() >= MAX_LEN || u.is_empty();
+        let alternative = if exhausted || rule.alternatives.len() == 1 {
+            rule.shortest
+        } else {
+            u.int_in_range(0..=rule.alternatives.len() - 1)?
+        };
+        for symbol in &rule.alternatives[alternative] {
+            match symbol {
+                Symbol::Text(text) => output.push_str(text),
+                Symbol::Class(ranges) => {
+                    let size: u32 = ranges
+                        .iter()
+                        .map(|&(start, end)| end as u32 - start as u32 + 1)
+                        .sum();
+                    let mut offset = u.int_in_range(0..=size - 1)?;
+                    for &(start, end) in ranges {
+                        let len = end as u32 - start as u32 + 1;
+                        if offset < len {
+                            // Surrogates can not be encoded, so they are replaced
+                            output.push(
+                                std::char::from_u32(start as u32 + offset).unwrap_or('\u{fffd}'),
+                            );
+                            break;
+                        }
+                        offset -= len;
+                    }
+                }
+                Symbol::Rule(rule) => self.expand(*rule, u, depth + 1, output)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_alternatives(source: &str) -> std::result::Result<Vec<Vec<Token>>, String> {
+    let mut alternatives = vec![Vec::new()];
+    let mut chars = source.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '|' => {
+                chars.next();
+                alternatives.push(Vec::new());
+            }
+            '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => text.push(parse_escape(&mut chars)?),
+                        Some(c) => text.push(c),
+                        None => return Err("unterminated string literal".to_string()),
+                    }
+                }
+                let alternative = alternatives.last_mut().unwrap();
+                alternative.push(Token::Symbol(Symbol::Text(text)));
+            }
+            '[' => {
+                chars.next();
+                let mut ranges = Vec::new();
+                loop {
+                    let start = match chars.next() {
+                        Some(']') => break,
+                        Some('\\') => parse_escape(&mut chars)?,
+                        Some(c) => c,
+                        None => return Err("unterminated character class".to_string()),
+                    };
+                    let mut end = start;
+                    if chars.peek() == Some(&'-') {
+                        chars.next();
+                        end = match chars.next() {
+                            Some('\\') => parse_escape(&mut chars)?,
+                            Some(']') | None => {
+                                return Err("unterminated character range".to_string())
+                            }
+                            Some(c) => c,
+                        };
+                    }
+                    if end < start {
+                        return Err(format!("empty character range `{}-{}`", start, end));
+                    }
+                    ranges.push((start, end));
+                }
+                if ranges.is_empty() {
+                    return Err("empty character class".to_string());
+                }
+                let alternative = alternatives.last_mut().unwrap();
+                alternative.push(Token::Symbol(Symbol::Class(ranges)));
+            }
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    name.push(c);
+                    chars.next();
+                }
+                alternatives.last_mut().unwrap().push(Token::Name(name));
+            }
+            c => return Err(format!("unexpected character `{}`", c)),
+        }
+    }
+    Ok(alternatives)
+}
+
+fn parse_escape(chars: &mut Peekable<Chars>) -> std::result::Result<char, String> {
+    match chars.next() {
+        Some('n') => Ok('\n'),
+        Some('t') => Ok('\t'),
+        Some('r') => Ok('\r'),
+        Some('u') => {
+            if chars.next() != Some('{') {
+                return Err("expected `\\u{...}`".to_string());
+            }
+            let mut hex = String::new();
+            loop {
+                match chars.next() {
+                    Some('}') => break,
+                    Some(c) => hex.push(c),
+                    None => return Err("unterminated unicode escape".to_string()),
+                }
+            }
+            u32::from_str_radix(&hex, 16)
+                .ok()
+                .and_then(std::char::from_u32)
+                .ok_or_else(|| format!("invalid unicode escape `\\u{{{}}}`", hex))
+        }
+        Some(c) => Ok(c),
+        None => Err("unterminated escape".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate(grammar: &LazyGrammar, data: &[u8]) -> String {
+        grammar.generate(&mut Unstructured::new(data)).unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let grammar = Grammar::parse(
+            r#"
+            # Comments and blank lines are skipped
+            list ::= "[" items "]" | "[]"
+                | "null"
+            items ::= item | item "," items
+            item ::= [a-c0-9] | "\u{1F600}\"\\"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(grammar.rules.len(), 3);
+        assert_eq!(grammar.rules[0].alternatives.len(), 3);
+        assert_eq!(grammar.rules[0].shortest, 1);
+        assert_eq!(
+            grammar.rules[2].alternatives,
+            [
+                vec![Symbol::Class(vec![('a', 'c'), ('0', '9')])],
+                vec![Symbol::Text("\u{1F600}\"\\".to_string())],
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let errors = [
+            ("", "grammar has no rules"),
+            ("a ::= b", "rule `b` is not defined"),
+            ("a ::= \"x", "line 1: unterminated string literal"),
+            ("a ::= [z-a]", "line 1: empty character range `z-a`"),
+            (
+                "a ::= \"x\"\na ::= \"y\"",
+                "line 2: rule `a` is defined twice",
+            ),
+            ("| \"x\"", "line 1: alternatives before the first rule"),
+            ("a ::= \"x\" b\nb ::= \"y\" b", "rule `a` never terminates"),
+        ];
+        for (source, error) in errors.iter() {
+            assert_eq!(Grammar::parse(source).unwrap_err(), *error);
+        }
+    }
+
+    #[test]
+    fn generation() {
+        static LIST: LazyGrammar = LazyGrammar::new(
+            r#"
+            list ::= "[" items "]" | "[]"
+            items ::= item | item "," items
+            item ::= [a-c] | "x"
+            "#,
+        );
+        // Exhausted input takes the shortest alternatives
+        assert_eq!(generate(&LIST, &[]), "[]");
+        assert_eq!(generate(&LIST, &[0, 1, 0, 2, 0, 0, 0, 1, 0]), "[c,a]");
+        // Recursion stops at the depth limit, even with endless input
+        let mut data = vec![0];
+        for _ in 0..1000 {
+            data.extend_from_slice(&[1, 0, 0]);
+        }
+        let list = generate(&LIST, &data);
+        assert_eq!(list, format!("[{}a]", "a,".repeat(MAX_DEPTH - 1)));
+    }
+
+    #[test]
+    fn builtins() {
+        for grammar in [&JSON, &URL, &SQL].iter() {
+            let mut state: u32 = 1;
+            for _ in 0..100 {
+                let data: Vec<u8> = (0..64)
+                    .map(|_| {
+                        state ^= state << 13;
+                        state ^= state >> 17;
+                        state ^= state << 5;
+                        state as u8
+                    })
+                    .collect();
+                generate(grammar, &data);
+            }
+        }
+        assert_eq!(generate(&JSON, &[]), "true");
+        assert_eq!(generate(&URL, &[]), "http://localhost");
+        assert_eq!(generate(&SQL, &[]), "DELETE FROM a");
+    }
+}
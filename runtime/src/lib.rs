@@ -0,0 +1,7 @@
+//! Runtime support for code generated by `auto-fuzz-test`. Procedural macro crates can only
+//! export macros, so traits and helpers used by the generated code live here.
+
+pub mod adapter;
+pub mod closure;
+pub mod grammar;
+pub mod seed;
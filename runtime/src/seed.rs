@@ -0,0 +1,383 @@
+//! Recording of fuzzing seeds from `cargo test`, enabled by `--cfg auto_fuzz_seeds`.
+//!
+//! Arguments of calls to fuzzed functions are encoded in the byte format, which the `Arbitrary`
+//! impl of the fuzz struct decodes back to the same values.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Upper bound of seeds per target, so tests calling a function in a loop do not flood the corpus.
+const MAX_SEEDS: usize = 256;
+
+/// Values, which can be encoded into input of `arbitrary` 1.x.
+pub trait Seed {
+    fn encode(&self, encoder: &mut Encoder);
+}
+
+/// Builder of an input for `arbitrary::Unstructured`.
+#[derive(Debug, Default)]
+pub struct Encoder {
+    data: Vec<u8>,
+    /// Collection lengths, which `arbitrary` takes from the end of the input, along with the
+    /// number of bytes read from the front at the moment.
+    lengths: Vec<(usize, usize)>,
+}
+
+impl Encoder {
+    pub fn new() -> Encoder {
+        Encoder::default()
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    /// Length of a collection, which is read by `Unstructured::arbitrary_len`.
+    pub fn length(&mut self, length: usize) {
+        self.lengths.push((self.data.len(), length));
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        let mut input = self.data;
+        let front_len = input.len();
+        // The first length is read from the very end, so the last one is placed right after the
+        // front. Its encoding depends on the number of bytes left at the moment.
+        let mut suffix_len = 0;
+        for &(offset, length) in self.lengths.iter().rev() {
+            let rest = front_len - offset + suffix_len;
+            let size = [1, 2, 4]
+                .iter()
+                .copied()
+                .find(|&size: &usize| {
+                    rest as u64 + size as u64 <= (1u64 << (size * 8)) - 1 + size as u64
+                })
+                .unwrap_or(8);
+            // Only as many bytes are used, as needed to cover `0..=rest`
+            let used = (0..size)
+                .take_while(|&byte| (rest as u64) >> (byte * 8) > 0)
+                .count();
+            let bytes = (length as u64).to_be_bytes();
+            input.extend_from_slice(&bytes[8 - used..]);
+            input.resize(input.len() + size - used, 0);
+            suffix_len += size;
+        }
+        input
+    }
+}
+
+/// Wrapper, which lets generated code check for a `Seed` impl on concrete argument types:
+/// `(&Probe(&arg)).encode_seed(encoder)` resolves to `EncodeSeed` if there is one, and to
+/// `NoSeed` otherwise.
+pub struct Probe<'a, T: ?Sized>(pub &'a T);
+
+pub trait EncodeSeed {
+    fn encode_seed(&self, encoder: &mut Encoder) -> bool;
+}
+
+impl<T: Seed + ?Sized> EncodeSeed for Probe<'_, T> {
+    fn encode_seed(&self, encoder: &mut Encoder) -> bool {
+        self.0.encode(encoder);
+        true
+    }
+}
+
+pub trait NoSeed {
+    fn encode_seed(&self, _encoder: &mut Encoder) -> bool {
+        false
+    }
+}
+
+impl<T: ?Sized> NoSeed for &Probe<'_, T> {}
+
+thread_local! {
+    static PAUSED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Stops recording on the current thread, until the guard is dropped. Property tests feed random
+/// inputs, which are no better seeds than the ones found by the fuzzer.
+pub fn pause() -> Paused {
+    Paused {
+        previous: PAUSED.with(|paused| paused.replace(true)),
+    }
+}
+
+pub struct Paused {
+    previous: bool,
+}
+
+impl Drop for Paused {
+    fn drop(&mut self) {
+        PAUSED.with(|paused| paused.set(self.previous));
+    }
+}
+
+/// Writes the input into `seed_dir`, unless it is already there.
+pub fn record(target: &str, seed_dir: &str, encoder: Encoder) {
+    static RECORDED: Mutex<Option<HashMap<String, usize>>> = Mutex::new(None);
+
+    if PAUSED.with(Cell::get) {
+        return;
+    }
+    let input = encoder.finish();
+    let path = Path::new(seed_dir).join(format!("seed-{:016x}", fnv1a(&input)));
+    if path.exists() {
+        return;
+    }
+    {
+        let mut recorded = RECORDED.lock().unwrap_or_else(|error| error.into_inner());
+        let count = recorded
+            .get_or_insert_with(HashMap::new)
+            .entry(target.to_string())
+            .or_insert(0);
+        if *count >= MAX_SEEDS {
+            return;
+        }
+        *count += 1;
+    }
+    if let Err(error) = fs::create_dir_all(seed_dir).and_then(|_| fs::write(&path, &input)) {
+        eprintln!("Failed to record seed {}: {}", path.display(), error);
+    }
+}
+
+/// Stable hash for seed names, so repeated test runs do not duplicate seeds.
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+macro_rules! impl_seed_for_integers {
+    ($($ty:ty)*) => {
+        $(
+            impl Seed for $ty {
+                fn encode(&self, encoder: &mut Encoder) {
+                    encoder.bytes(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_seed_for_integers!(u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+
+// `arbitrary` reads pointer-sized integers as 64 bit ones on every platform
+impl Seed for usize {
+    fn encode(&self, encoder: &mut Encoder) {
+        (*self as u64).encode(encoder);
+    }
+}
+
+impl Seed for isize {
+    fn encode(&self, encoder: &mut Encoder) {
+        (*self as i64).encode(encoder);
+    }
+}
+
+impl Seed for f32 {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.to_bits().encode(encoder);
+    }
+}
+
+impl Seed for f64 {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.to_bits().encode(encoder);
+    }
+}
+
+impl Seed for bool {
+    fn encode(&self, encoder: &mut Encoder) {
+        (*self as u8).encode(encoder);
+    }
+}
+
+impl Seed for char {
+    fn encode(&self, encoder: &mut Encoder) {
+        (*self as u32).encode(encoder);
+    }
+}
+
+impl Seed for () {
+    fn encode(&self, _encoder: &mut Encoder) {}
+}
+
+impl Seed for str {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.length(self.len());
+        encoder.bytes(self.as_bytes());
+    }
+}
+
+impl Seed for String {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.as_str().encode(encoder);
+    }
+}
+
+impl<T: Seed> Seed for [T] {
+    fn encode(&self, encoder: &mut Encoder) {
+        // Elements of collections are preceded by a "keep going" flag
+        for element in self {
+            true.encode(encoder);
+            element.encode(encoder);
+        }
+        false.encode(encoder);
+    }
+}
+
+impl<T: Seed> Seed for Vec<T> {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.as_slice().encode(encoder);
+    }
+}
+
+impl<T: Seed, const N: usize> Seed for [T; N] {
+    fn encode(&self, encoder: &mut Encoder) {
+        for element in self {
+            element.encode(encoder);
+        }
+    }
+}
+
+impl<T: Seed> Seed for Option<T> {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.is_some().encode(encoder);
+        if let Some(value) = self {
+            value.encode(encoder);
+        }
+    }
+}
+
+impl<T: Seed + ?Sized> Seed for Box<T> {
+    fn encode(&self, encoder: &mut Encoder) {
+        (**self).encode(encoder);
+    }
+}
+
+impl<T: Seed + ?Sized> Seed for &T {
+    fn encode(&self, encoder: &mut Encoder) {
+        (**self).encode(encoder);
+    }
+}
+
+impl<T: Seed + ?Sized> Seed for &mut T {
+    fn encode(&self, encoder: &mut Encoder) {
+        (**self).encode(encoder);
+    }
+}
+
+macro_rules! impl_seed_for_tuples {
+    ($(($($name:ident)+))*) => {
+        $(
+            impl<$($name: Seed),+> Seed for ($($name,)+) {
+                #[allow(non_snake_case)]
+                fn encode(&self, encoder: &mut Encoder) {
+                    let ($($name,)+) = self;
+                    $($name.encode(encoder);)+
+                }
+            }
+        )*
+    };
+}
+
+impl_seed_for_tuples! {
+    (A)
+    (A B)
+    (A B C)
+    (A B C D)
+    (A B C D E)
+    (A B C D E F)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arbitrary::{Arbitrary, Unstructured};
+    use std::fmt::Debug;
+    use tempfile::tempdir;
+
+    fn encode<T: Seed + ?Sized>(value: &T) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        value.encode(&mut encoder);
+        encoder.finish()
+    }
+
+    fn round_trip<T>(value: T)
+    where
+        T: Seed + Debug + PartialEq + for<'a> Arbitrary<'a>,
+    {
+        let input = encode(&value);
+        let mut u = Unstructured::new(&input);
+        assert_eq!(T::arbitrary(&mut u).unwrap(), value);
+        assert!(u.is_empty(), "{:?} left unread input", value);
+    }
+
+    #[test]
+    fn primitives() {
+        round_trip(0x1234_5678u32);
+        round_trip(-5i8);
+        round_trip(u128::MAX);
+        round_trip(usize::MAX);
+        round_trip(-1isize);
+        round_trip(1.5f64);
+        round_trip(true);
+        round_trip(false);
+        round_trip('ж');
+        round_trip(());
+    }
+
+    #[test]
+    fn collections() {
+        round_trip(String::new());
+        round_trip("hello".to_string());
+        round_trip(vec![1u16, 2, 3]);
+        round_trip(vec!["a".to_string(), String::new(), "bc".to_string()]);
+        round_trip(Some(vec![Some('x'), None]));
+        round_trip([7u8, 8, 9]);
+        round_trip(Box::new(42u64));
+        round_trip(("key".to_string(), 3u8, vec![true]));
+    }
+
+    #[test]
+    fn long_strings() {
+        // Lengths take more bytes, as the input grows
+        round_trip("x".repeat(300));
+        round_trip(("y".repeat(70_000), "z".repeat(10), 5u32));
+    }
+
+    #[test]
+    // The borrow is what generated code relies on to fall back to `NoSeed`
+    #[allow(clippy::needless_borrow)]
+    fn probe() {
+        use super::{EncodeSeed as _, NoSeed as _};
+
+        struct Opaque;
+        let mut encoder = Encoder::new();
+        assert!((&Probe(&&5u8)).encode_seed(&mut encoder));
+        assert!(!(&Probe(&Opaque)).encode_seed(&mut encoder));
+        assert_eq!(encoder.finish(), [5]);
+    }
+
+    #[test]
+    fn record_once() {
+        let dir = tempdir().unwrap();
+        let dir = dir.path().join("corpus").join("target");
+        let seed_dir = dir.to_str().unwrap();
+        for _ in 0..2 {
+            let mut encoder = Encoder::new();
+            7u8.encode(&mut encoder);
+            record("target", seed_dir, encoder);
+        }
+        {
+            let _paused = pause();
+            let mut encoder = Encoder::new();
+            8u8.encode(&mut encoder);
+            record("target", seed_dir, encoder);
+        }
+        let seeds: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(seeds.len(), 1);
+    }
+}
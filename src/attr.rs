@@ -0,0 +1,565 @@
+use crate::crate_parse::{Backend, DictionaryScope};
+use crate::grammar;
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::env;
+use std::fs;
+use syn::parse::{Parse, ParseStream};
+use syn::{
+    Attribute, Expr, ExprLit, ExprRange, ExprUnary, FnArg, Ident, Lit, LitBool, LitStr, Path,
+    RangeLimits, Signature, Token, UnOp,
+};
+
+/// Arguments of harness attributes: an optional module path of the fuzzed item, followed by
+/// comma separated `key = value` options, like `#[create_cargofuzz_harness(foo::bar, backend = "honggfuzz")]`.
+#[derive(Default)]
+pub struct HarnessAttr {
+    pub module_path: TokenStream,
+    pub backend: Option<Backend>,
+    pub property_tests: Option<bool>,
+    pub seeds: Option<bool>,
+    pub dictionary: Option<DictionaryScope>,
+    pub executor: Option<Path>,
+    pub setup: Option<Path>,
+    /// Predicate, which guards calls of a fuzzed `unsafe fn`. Only single functions take it.
+    pub unsafe_precondition: Option<Path>,
+}
+
+impl HarnessAttr {
+    /// Parses the value of an option, which is shared by all harness macros. Returns `false`
+    /// for unknown keys.
+    fn parse_option(&mut self, key: &Ident, input: ParseStream) -> syn::Result<bool> {
+        match key.to_string().as_str() {
+            "backend" => {
+                let value: LitStr = input.parse()?;
+                let backend = value
+                    .value()
+                    .parse()
+                    .map_err(|error| syn::Error::new(value.span(), error))?;
+                self.backend = Some(backend);
+            }
+            "property_tests" => {
+                let value: LitBool = input.parse()?;
+                self.property_tests = Some(value.value);
+            }
+            "seeds" => {
+                let value: LitBool = input.parse()?;
+                self.seeds = Some(value.value);
+            }
+            "dictionary" => {
+                let value: LitStr = input.parse()?;
+                let dictionary = value
+                    .value()
+                    .parse()
+                    .map_err(|error| syn::Error::new(value.span(), error))?;
+                self.dictionary = Some(dictionary);
+            }
+            "executor" => {
+                let value: LitStr = input.parse()?;
+                self.executor = Some(value.parse()?);
+            }
+            "setup" => {
+                let value: LitStr = input.parse()?;
+                self.setup = Some(value.parse()?);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+impl Parse for HarnessAttr {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut attr = HarnessAttr::default();
+
+        // Module path goes first, so anything except for `key = value` is considered to be a path
+        let mut first = true;
+        if !(input.is_empty() || (input.peek(Ident) && input.peek2(Token![=]))) {
+            let path = input.call(Path::parse_mod_style)?;
+            attr.module_path = quote!(#path);
+            first = false;
+        }
+
+        while !input.is_empty() {
+            if !first {
+                input.parse::<Token![,]>()?;
+                if input.is_empty() {
+                    break;
+                }
+            }
+            first = false;
+
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            if key == "unsafe_precondition" {
+                attr.unsafe_precondition = Some(input.parse()?);
+            } else if !attr.parse_option(&key, input)? {
+                return Err(unknown_option(&key));
+            }
+        }
+
+        Ok(attr)
+    }
+}
+
+/// Arguments of the crate harness macro: harness options along with glob patterns for module
+/// or item paths, like `create_cargofuzz_crate_harness!(include = "parser::*", exclude = "parser::legacy")`.
+#[derive(Default)]
+pub struct CrateHarnessAttr {
+    pub options: HarnessAttr,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl Parse for CrateHarnessAttr {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut attr = CrateHarnessAttr::default();
+
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            match key.to_string().as_str() {
+                "include" => attr.include.push(input.parse::<LitStr>()?.value()),
+                "exclude" => attr.exclude.push(input.parse::<LitStr>()?.value()),
+                _ => {
+                    if !attr.options.parse_option(&key, input)? {
+                        return Err(unknown_option(&key));
+                    }
+                }
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(attr)
+    }
+}
+
+/// Grammars, which `auto_fuzz_test_runtime::grammar` ships.
+const BUILTIN_GRAMMARS: &[&str] = &["json", "url", "sql"];
+
+/// Domain of a fuzzed argument, like `#[fuzz(range = 2..=36)]` or `#[fuzz(max_len = 4096)]`,
+/// or a custom generator of its values, like `#[fuzz(with = my_gen)]` or `#[fuzz(grammar = "json")]`.
+#[derive(Default)]
+pub struct ArgConstraint {
+    pub range: Option<ExprRange>,
+    pub max_len: Option<Expr>,
+    /// `fn(&mut Unstructured) -> arbitrary::Result<T>`
+    pub with: Option<Path>,
+    pub grammar: Option<Grammar>,
+}
+
+/// Grammar of string arguments.
+#[derive(Debug, PartialEq)]
+pub enum Grammar {
+    /// Name of one of `BUILTIN_GRAMMARS`.
+    Builtin(String),
+    /// Path of a grammar file, relative to the crate root.
+    File(LitStr),
+}
+
+impl ArgConstraint {
+    /// Collects `#[fuzz(...)]` attributes of an argument, returns `None` if there are none.
+    pub fn from_attrs(attrs: &[Attribute]) -> syn::Result<Option<ArgConstraint>> {
+        let mut constraint = None;
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("fuzz")) {
+            let constraint = constraint.get_or_insert_with(ArgConstraint::default);
+            attr.parse_args_with(|input: ParseStream| constraint.parse_options(input))?;
+            let options = [
+                constraint.range.is_some(),
+                constraint.max_len.is_some(),
+                constraint.with.is_some(),
+                constraint.grammar.is_some(),
+            ];
+            if options.iter().filter(|&&set| set).count() > 1 {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`range`, `max_len`, `with` and `grammar` can not be combined",
+                ));
+            }
+        }
+        Ok(constraint)
+    }
+
+    /// Whether values are decoded from the same bytes, as without the constraint.
+    pub fn keeps_encoding(&self) -> bool {
+        self.range.is_none() && self.with.is_none() && self.grammar.is_none()
+    }
+
+    fn set_grammar(&mut self, key: &Ident, grammar: Grammar) -> syn::Result<()> {
+        if self.grammar.is_some() {
+            return Err(syn::Error::new(
+                key.span(),
+                "`grammar` and `grammar_file` can not be combined",
+            ));
+        }
+        self.grammar = Some(grammar);
+        Ok(())
+    }
+
+    fn parse_options(&mut self, input: ParseStream) -> syn::Result<()> {
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            match key.to_string().as_str() {
+                "range" => match input.parse()? {
+                    Expr::Range(range) => {
+                        check_range(&range)?;
+                        self.range = Some(range);
+                    }
+                    value => {
+                        return Err(syn::Error::new_spanned(value, "Expected a range"));
+                    }
+                },
+                "max_len" => self.max_len = Some(input.parse()?),
+                "with" => self.with = Some(input.parse()?),
+                "grammar" => {
+                    let value: LitStr = input.parse()?;
+                    if !BUILTIN_GRAMMARS.contains(&value.value().as_str()) {
+                        return Err(syn::Error::new(
+                            value.span(),
+                            format!(
+                                "Unknown grammar, expected one of {:?}, or `grammar_file = \"path\"`",
+                                BUILTIN_GRAMMARS
+                            ),
+                        ));
+                    }
+                    self.set_grammar(&key, Grammar::Builtin(value.value()))?;
+                }
+                "grammar_file" => {
+                    let value: LitStr = input.parse()?;
+                    self.set_grammar(&key, Grammar::File(value))?;
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        format!("Unknown argument constraint `{}`", key),
+                    ))
+                }
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rejects ranges with literal bounds, which hold no values, like `5..5` or `10..=5`. Other
+/// bounds are checked by the generated code.
+fn check_range(range: &ExprRange) -> syn::Result<()> {
+    let bounds = range
+        .from
+        .as_deref()
+        .and_then(literal_bound)
+        .zip(range.to.as_deref().and_then(literal_bound));
+    if let Some((start, end)) = bounds {
+        let is_empty = match range.limits {
+            RangeLimits::HalfOpen(_) => start >= end,
+            RangeLimits::Closed(_) => start > end,
+        };
+        if is_empty {
+            return Err(syn::Error::new_spanned(range, "Range has no values"));
+        }
+    }
+    Ok(())
+}
+
+/// Value of an integer literal, like `36` or `-1`.
+pub fn literal_bound(bound: &Expr) -> Option<i128> {
+    match bound {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(int), ..
+        }) => int.base10_parse().ok(),
+        Expr::Unary(ExprUnary {
+            op: UnOp::Neg(_),
+            expr,
+            ..
+        }) => literal_bound(expr).map(|value| -value),
+        Expr::Paren(paren) => literal_bound(&paren.expr),
+        _ => None,
+    }
+}
+
+/// Reports the first invalid `#[fuzz(...)]` attribute of arguments with its span.
+pub fn check_arg_attrs(signature: &Signature) -> syn::Result<()> {
+    for input in &signature.inputs {
+        if let FnArg::Typed(arg) = input {
+            if let Some(constraint) = ArgConstraint::from_attrs(&arg.attrs)? {
+                if let Some(Grammar::File(path)) = &constraint.grammar {
+                    check_grammar_file(path)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses a grammar file, which would otherwise fail on every input of the fuzzer.
+fn check_grammar_file(path: &LitStr) -> syn::Result<()> {
+    let crate_root = env::current_dir().expect("Failed to obtain project root dir");
+    let source = fs::read_to_string(crate_root.join(path.value())).map_err(|error| {
+        syn::Error::new(
+            path.span(),
+            format!("Failed to read grammar file: {}", error),
+        )
+    })?;
+    grammar::Grammar::parse(&source)
+        .map_err(|error| syn::Error::new(path.span(), format!("Invalid grammar: {}", error)))?;
+    Ok(())
+}
+
+/// Removes `#[fuzz(...)]` attributes of arguments, which only the harness macros understand.
+pub fn strip_arg_attrs(signature: &mut Signature) {
+    for input in signature.inputs.iter_mut() {
+        if let FnArg::Typed(arg) = input {
+            arg.attrs.retain(|attr| !attr.path.is_ident("fuzz"));
+        }
+    }
+}
+
+fn unknown_option(key: &Ident) -> syn::Error {
+    syn::Error::new(key.span(), format!("Unknown harness option `{}`", key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use syn::parse::Parser;
+
+    #[test]
+    fn empty() {
+        let attr: HarnessAttr = syn::parse2(quote!()).unwrap();
+        assert!(attr.module_path.is_empty());
+        assert_eq!(attr.backend, None);
+    }
+
+    #[test]
+    fn module_path() {
+        let attr: HarnessAttr = syn::parse2(quote!(foo::bar)).unwrap();
+        assert_eq!(attr.module_path.to_string(), quote!(foo::bar).to_string());
+        assert_eq!(attr.backend, None);
+    }
+
+    #[test]
+    fn backend() {
+        let attr: HarnessAttr = syn::parse2(quote!(backend = "honggfuzz")).unwrap();
+        assert!(attr.module_path.is_empty());
+        assert_eq!(attr.backend, Some(Backend::Honggfuzz));
+
+        let attr: HarnessAttr = syn::parse2(quote!(foo::bar, backend = "libfuzzer",)).unwrap();
+        assert_eq!(attr.module_path.to_string(), quote!(foo::bar).to_string());
+        assert_eq!(attr.backend, Some(Backend::LibFuzzer));
+
+        let attr: HarnessAttr = syn::parse2(quote!(backend = "afl")).unwrap();
+        assert_eq!(attr.backend, Some(Backend::Afl));
+    }
+
+    #[test]
+    fn property_tests() {
+        let attr: HarnessAttr = syn::parse2(quote!(foo::bar, property_tests = true)).unwrap();
+        assert_eq!(attr.property_tests, Some(true));
+        assert_eq!(attr.backend, None);
+
+        let attr: HarnessAttr =
+            syn::parse2(quote!(backend = "afl", property_tests = false)).unwrap();
+        assert_eq!(attr.property_tests, Some(false));
+        assert_eq!(attr.backend, Some(Backend::Afl));
+
+        assert!(syn::parse2::<HarnessAttr>(quote!(property_tests = "yes")).is_err());
+    }
+
+    #[test]
+    fn seeds() {
+        let attr: HarnessAttr = syn::parse2(quote!(seeds = true, property_tests = true)).unwrap();
+        assert_eq!(attr.seeds, Some(true));
+        assert_eq!(attr.property_tests, Some(true));
+
+        let attr: HarnessAttr = syn::parse2(quote!(foo::bar)).unwrap();
+        assert_eq!(attr.seeds, None);
+    }
+
+    #[test]
+    fn dictionary() {
+        let attr: HarnessAttr = syn::parse2(quote!(foo::bar, dictionary = "module")).unwrap();
+        assert_eq!(attr.dictionary, Some(DictionaryScope::Module));
+
+        let attr: CrateHarnessAttr =
+            syn::parse2(quote!(include = "foo*", dictionary = "none")).unwrap();
+        assert_eq!(attr.options.dictionary, Some(DictionaryScope::None));
+
+        assert!(syn::parse2::<HarnessAttr>(quote!(dictionary = "crate")).is_err());
+    }
+
+    #[test]
+    fn executor() {
+        let attr: HarnessAttr =
+            syn::parse2(quote!(foo::bar, executor = "tokio_test::block_on")).unwrap();
+        let executor = attr.executor.unwrap();
+        assert_eq!(
+            quote!(#executor).to_string(),
+            quote!(tokio_test::block_on).to_string()
+        );
+
+        assert!(syn::parse2::<HarnessAttr>(quote!(executor = "not a path")).is_err());
+        assert!(syn::parse2::<HarnessAttr>(quote!(executor = block_on)).is_err());
+    }
+
+    #[test]
+    fn setup() {
+        let attr: HarnessAttr = syn::parse2(quote!(setup = "crate::fuzz_setup")).unwrap();
+        let setup = attr.setup.unwrap();
+        assert_eq!(
+            quote!(#setup).to_string(),
+            quote!(crate::fuzz_setup).to_string()
+        );
+    }
+
+    #[test]
+    fn unsafe_precondition() {
+        let attr: HarnessAttr =
+            syn::parse2(quote!(unsafe_precondition = checks::is_utf8, seeds = true)).unwrap();
+        let precondition = attr.unsafe_precondition.unwrap();
+        assert_eq!(
+            quote!(#precondition).to_string(),
+            quote!(checks::is_utf8).to_string()
+        );
+        assert_eq!(attr.seeds, Some(true));
+
+        assert!(syn::parse2::<CrateHarnessAttr>(quote!(unsafe_precondition = is_utf8)).is_err());
+    }
+
+    #[test]
+    fn crate_harness() {
+        let attr: CrateHarnessAttr = syn::parse2(quote!()).unwrap();
+        assert!(attr.include.is_empty());
+        assert!(attr.exclude.is_empty());
+
+        let attr: CrateHarnessAttr = syn::parse2(quote!(
+            include = "foo::*",
+            include = "bar",
+            exclude = "foo::internal*",
+            backend = "honggfuzz",
+        ))
+        .unwrap();
+        assert_eq!(attr.include, ["foo::*", "bar"]);
+        assert_eq!(attr.exclude, ["foo::internal*"]);
+        assert_eq!(attr.options.backend, Some(Backend::Honggfuzz));
+
+        assert!(syn::parse2::<CrateHarnessAttr>(quote!(foo::bar)).is_err());
+        assert!(syn::parse2::<CrateHarnessAttr>(quote!(include = foo)).is_err());
+        assert!(syn::parse2::<CrateHarnessAttr>(quote!(engine = "afl")).is_err());
+    }
+
+    #[test]
+    fn arg_constraint() {
+        let function: syn::ItemFn = syn::parse2(quote! {
+            fn f(
+                #[fuzz(range = 2..=36)] radix: u32,
+                #[doc = "digits"]
+                #[fuzz(max_len = 4096)]
+                digits: &str,
+                plain: bool,
+                #[fuzz(with = config::small)] config: Config,
+                #[fuzz(grammar = "json")] json: String,
+                #[fuzz(grammar_file = "fuzz/query.gram")] query: &str,
+            ) {}
+        })
+        .unwrap();
+        let constraints: Vec<_> = function
+            .sig
+            .inputs
+            .iter()
+            .map(|input| match input {
+                FnArg::Typed(arg) => ArgConstraint::from_attrs(&arg.attrs).unwrap(),
+                FnArg::Receiver(_) => unreachable!(),
+            })
+            .collect();
+        let range = &constraints[0].as_ref().unwrap().range;
+        assert_eq!(quote!(#range).to_string(), quote!(2..=36).to_string());
+        let max_len = &constraints[1].as_ref().unwrap().max_len;
+        assert_eq!(quote!(#max_len).to_string(), "4096");
+        assert!(constraints[2].is_none());
+        let with = &constraints[3].as_ref().unwrap().with;
+        assert_eq!(quote!(#with).to_string(), quote!(config::small).to_string());
+        assert!(constraints[1].as_ref().unwrap().keeps_encoding());
+        assert!(!constraints[3].as_ref().unwrap().keeps_encoding());
+        assert_eq!(
+            constraints[4].as_ref().unwrap().grammar,
+            Some(Grammar::Builtin("json".to_string()))
+        );
+        assert_eq!(
+            constraints[5].as_ref().unwrap().grammar,
+            Some(Grammar::File(syn::parse_quote!("fuzz/query.gram")))
+        );
+
+        let mut signature = function.sig;
+        strip_arg_attrs(&mut signature);
+        assert_eq!(
+            quote!(#signature).to_string(),
+            quote!(fn f(
+                radix: u32,
+                #[doc = "digits"]
+                digits: &str,
+                plain: bool,
+                config: Config,
+                json: String,
+                query: &str,
+            ))
+            .to_string()
+        );
+
+        let invalid = [
+            quote!(#[fuzz(range = 36)]),
+            quote!(#[fuzz(range = 5..5)]),
+            quote!(#[fuzz(range = 10..=5)]),
+            quote!(#[fuzz(range = -1..-2)]),
+            quote!(#[fuzz(min = 2)]),
+            quote!(#[fuzz(range = 2..=36, max_len = 4)]),
+            quote!(#[fuzz(max_len = 4)] #[fuzz(with = gen)]),
+            quote!(#[fuzz(with = 5)]),
+            quote!(#[fuzz(grammar = "yaml")]),
+            quote!(#[fuzz(grammar = "json", grammar_file = "json.gram")]),
+            quote!(#[fuzz(grammar = "json", max_len = 4)]),
+        ];
+        for attr in invalid {
+            let attrs = syn::Attribute::parse_outer.parse2(attr).unwrap();
+            assert!(ArgConstraint::from_attrs(&attrs).is_err());
+        }
+    }
+
+    #[test]
+    fn grammar_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let check = |source: Option<&str>| {
+            let path = dir.path().join("query.gram");
+            let _ = fs::remove_file(&path);
+            if let Some(source) = source {
+                fs::write(&path, source).unwrap();
+            }
+            let path = path.to_str().unwrap();
+            let function: syn::ItemFn = syn::parse2(quote! {
+                fn f(#[fuzz(grammar_file = #path)] query: &str) {}
+            })
+            .unwrap();
+            check_arg_attrs(&function.sig).map_err(|error| error.to_string())
+        };
+
+        assert_eq!(check(Some("query ::= \"SELECT \" [a-z]")), Ok(()));
+        assert_eq!(
+            check(Some("query ::= \"SELECT \" columns")),
+            Err("Invalid grammar: rule `columns` is not defined".to_string())
+        );
+        assert!(check(None)
+            .unwrap_err()
+            .starts_with("Failed to read grammar file"));
+    }
+
+    #[test]
+    fn unknown_option() {
+        assert!(syn::parse2::<HarnessAttr>(quote!(foo::bar, engine = "afl")).is_err());
+        assert!(syn::parse2::<HarnessAttr>(quote!(backend = "nonexistent")).is_err());
+    }
+}
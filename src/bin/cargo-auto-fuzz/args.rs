@@ -0,0 +1,279 @@
+/// Subcommands of `cargo auto-fuzz`.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Help,
+    List,
+    Build {
+        pattern: Option<String>,
+    },
+    Run {
+        pattern: String,
+        fuzzer_args: Vec<String>,
+    },
+    RunAll {
+        time_per_target: u64,
+    },
+    Campaign {
+        pattern: Option<String>,
+        slice: u64,
+        rounds: Option<u64>,
+    },
+    Triage {
+        pattern: Option<String>,
+    },
+    Coverage {
+        pattern: Option<String>,
+    },
+}
+
+pub const USAGE: &str = "Lists, builds and runs fuzzing targets generated by auto-fuzz-test
+
+Usage:
+    cargo auto-fuzz list
+    cargo auto-fuzz build [<pattern>]
+    cargo auto-fuzz run <pattern> [-- <fuzzer args>...]
+    cargo auto-fuzz run-all [--time-per-target <seconds>]
+    cargo auto-fuzz campaign [<pattern>] [--slice <seconds>] [--rounds <count>]
+    cargo auto-fuzz triage [<pattern>]
+    cargo auto-fuzz coverage [<pattern>]
+
+Patterns match target names, `*` stands for any sequence of characters and `?` for one character.";
+
+const DEFAULT_TIME_PER_TARGET: u64 = 60;
+const DEFAULT_SLICE: u64 = 60;
+
+impl Command {
+    /// Parses arguments, which follow the subcommand name (`cargo auto-fuzz` or `cargo-auto-fuzz`).
+    pub fn parse(args: &[String]) -> Result<Command, String> {
+        let (command, rest) = match args.split_first() {
+            Some((command, rest)) => (command.as_str(), rest),
+            None => return Ok(Command::Help),
+        };
+
+        match command {
+            "help" | "-h" | "--help" => Ok(Command::Help),
+            "list" => {
+                no_more_args(rest)?;
+                Ok(Command::List)
+            }
+            "build" | "triage" | "coverage" => {
+                let (pattern, rest) = match rest.split_first() {
+                    Some((pattern, rest)) => (Some(pattern.clone()), rest),
+                    None => (None, rest),
+                };
+                no_more_args(rest)?;
+                Ok(match command {
+                    "build" => Command::Build { pattern },
+                    "triage" => Command::Triage { pattern },
+                    _ => Command::Coverage { pattern },
+                })
+            }
+            "run" => {
+                let (pattern, rest) = rest
+                    .split_first()
+                    .ok_or_else(|| "`run` requires a target pattern".to_string())?;
+                let fuzzer_args = match rest.split_first() {
+                    Some((separator, fuzzer_args)) if separator == "--" => fuzzer_args.to_vec(),
+                    _ => {
+                        no_more_args(rest)?;
+                        Vec::new()
+                    }
+                };
+                Ok(Command::Run {
+                    pattern: pattern.clone(),
+                    fuzzer_args,
+                })
+            }
+            "run-all" => {
+                let mut time_per_target = DEFAULT_TIME_PER_TARGET;
+                for (option, value) in options(rest, &["--time-per-target"])? {
+                    if option == "--time-per-target" {
+                        time_per_target = parse_number(value)?;
+                    }
+                }
+                Ok(Command::RunAll { time_per_target })
+            }
+            "campaign" => {
+                let (pattern, rest) = match rest.split_first() {
+                    Some((pattern, rest)) if !pattern.starts_with("--") => {
+                        (Some(pattern.clone()), rest)
+                    }
+                    _ => (None, rest),
+                };
+                let mut slice = DEFAULT_SLICE;
+                let mut rounds = None;
+                for (option, value) in options(rest, &["--slice", "--rounds"])? {
+                    match option {
+                        "--slice" => slice = parse_number(value)?,
+                        _ => rounds = Some(parse_number(value)?),
+                    }
+                }
+                Ok(Command::Campaign {
+                    pattern,
+                    slice,
+                    rounds,
+                })
+            }
+            _ => Err(format!("Unknown command `{}`\n\n{}", command, USAGE)),
+        }
+    }
+}
+
+pub fn parse_number(value: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Expected a number, got `{}`", value))
+}
+
+/// Splits `--option value` and `--option=value` pairs, allowing only `known` options.
+fn options<'a>(args: &'a [String], known: &[&str]) -> Result<Vec<(&'a str, &'a str)>, String> {
+    let mut options = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (option, value) = match arg.split_once('=') {
+            Some((option, value)) => (option, value),
+            None => (
+                arg.as_str(),
+                args.next()
+                    .ok_or_else(|| format!("`{}` requires a value", arg))?
+                    .as_str(),
+            ),
+        };
+        if !known.contains(&option) {
+            return Err(format!("Unexpected argument `{}`", arg));
+        }
+        options.push((option, value));
+    }
+    Ok(options)
+}
+
+fn no_more_args(args: &[String]) -> Result<(), String> {
+    match args.first() {
+        Some(arg) => Err(format!("Unexpected argument `{}`", arg)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        Command::parse(&args)
+    }
+
+    #[test]
+    fn list_and_build() {
+        assert_eq!(parse(&[]), Ok(Command::Help));
+        assert_eq!(parse(&["list"]), Ok(Command::List));
+        assert!(parse(&["list", "foo"]).is_err());
+        assert_eq!(parse(&["build"]), Ok(Command::Build { pattern: None }));
+        assert_eq!(
+            parse(&["build", "foo*"]),
+            Ok(Command::Build {
+                pattern: Some("foo*".to_string())
+            })
+        );
+        assert!(parse(&["fuzz"]).is_err());
+    }
+
+    #[test]
+    fn run() {
+        assert!(parse(&["run"]).is_err());
+        assert_eq!(
+            parse(&["run", "foo*"]),
+            Ok(Command::Run {
+                pattern: "foo*".to_string(),
+                fuzzer_args: Vec::new()
+            })
+        );
+        assert_eq!(
+            parse(&["run", "foo*", "--", "-runs=100"]),
+            Ok(Command::Run {
+                pattern: "foo*".to_string(),
+                fuzzer_args: vec!["-runs=100".to_string()]
+            })
+        );
+        assert!(parse(&["run", "foo*", "bar"]).is_err());
+    }
+
+    #[test]
+    fn run_all() {
+        assert_eq!(
+            parse(&["run-all"]),
+            Ok(Command::RunAll {
+                time_per_target: DEFAULT_TIME_PER_TARGET
+            })
+        );
+        assert_eq!(
+            parse(&["run-all", "--time-per-target", "10"]),
+            Ok(Command::RunAll {
+                time_per_target: 10
+            })
+        );
+        assert_eq!(
+            parse(&["run-all", "--time-per-target=5"]),
+            Ok(Command::RunAll { time_per_target: 5 })
+        );
+        assert!(parse(&["run-all", "--time-per-target", "ten"]).is_err());
+        assert!(parse(&["run-all", "--time-per-target"]).is_err());
+        assert!(parse(&["run-all", "--slice", "10"]).is_err());
+    }
+
+    #[test]
+    fn campaign() {
+        assert_eq!(
+            parse(&["campaign"]),
+            Ok(Command::Campaign {
+                pattern: None,
+                slice: DEFAULT_SLICE,
+                rounds: None
+            })
+        );
+        assert_eq!(
+            parse(&["campaign", "foo*", "--slice=30", "--rounds", "2"]),
+            Ok(Command::Campaign {
+                pattern: Some("foo*".to_string()),
+                slice: 30,
+                rounds: Some(2)
+            })
+        );
+        assert_eq!(
+            parse(&["campaign", "--rounds", "1"]),
+            Ok(Command::Campaign {
+                pattern: None,
+                slice: DEFAULT_SLICE,
+                rounds: Some(1)
+            })
+        );
+        assert!(parse(&["campaign", "foo", "bar"]).is_err());
+    }
+
+    #[test]
+    fn triage() {
+        assert_eq!(parse(&["triage"]), Ok(Command::Triage { pattern: None }));
+        assert_eq!(
+            parse(&["triage", "foo*"]),
+            Ok(Command::Triage {
+                pattern: Some("foo*".to_string())
+            })
+        );
+        assert!(parse(&["triage", "foo", "bar"]).is_err());
+    }
+
+    #[test]
+    fn coverage() {
+        assert_eq!(
+            parse(&["coverage"]),
+            Ok(Command::Coverage { pattern: None })
+        );
+        assert_eq!(
+            parse(&["coverage", "foo*"]),
+            Ok(Command::Coverage {
+                pattern: Some("foo*".to_string())
+            })
+        );
+        assert!(parse(&["coverage", "foo", "--html"]).is_err());
+    }
+}
@@ -0,0 +1,226 @@
+use crate::crate_parse::CrateInfo;
+use crate::target::Target;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::value::{Table, Value};
+
+/// Targets, whose corpus did not grow for this many slices in a row, are considered stale.
+const STALE_AFTER: u64 = 3;
+/// Stale targets still get a slice every this many rounds, as they may start growing again.
+const STALE_ROUND_PERIOD: u64 = 4;
+
+/// Statistics of a single target, which are kept between campaigns.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TargetStats {
+    pub runs: u64,
+    pub seconds: u64,
+    pub corpus_size: u64,
+    /// Number of corpus entries, which were added during the last slice.
+    pub last_growth: u64,
+    /// Number of the last slices in a row, which did not grow the corpus.
+    pub stale_runs: u64,
+}
+
+impl TargetStats {
+    fn is_stale(&self) -> bool {
+        self.stale_runs >= STALE_AFTER
+    }
+}
+
+/// Statistics of all targets of a fuzzing crate, stored in `campaign.toml` next to its Cargo.toml.
+#[derive(Debug, PartialEq)]
+pub struct Stats {
+    path: PathBuf,
+    targets: BTreeMap<String, TargetStats>,
+}
+
+impl Stats {
+    pub fn load(fuzz_crate_dir: &Path) -> Result<Stats, String> {
+        let path = fuzz_crate_dir.join("campaign.toml");
+        let mut targets = BTreeMap::new();
+        if path.is_file() {
+            let contents = fs::read_to_string(&path)
+                .map_err(|error| format!("Failed to read {}: {}", path.display(), error))?;
+            let value: Value = contents
+                .parse()
+                .map_err(|error| format!("Failed to parse {}: {}", path.display(), error))?;
+            if let Some(table) = value.get("targets").and_then(Value::as_table) {
+                for (name, stats) in table {
+                    let field = |key: &str| {
+                        stats
+                            .get(key)
+                            .and_then(Value::as_integer)
+                            .unwrap_or(0)
+                            .max(0) as u64
+                    };
+                    targets.insert(
+                        name.clone(),
+                        TargetStats {
+                            runs: field("runs"),
+                            seconds: field("seconds"),
+                            corpus_size: field("corpus_size"),
+                            last_growth: field("last_growth"),
+                            stale_runs: field("stale_runs"),
+                        },
+                    );
+                }
+            }
+        }
+        Ok(Stats { path, targets })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let mut targets = Table::new();
+        for (name, stats) in &self.targets {
+            let mut table = Table::new();
+            table.insert("runs".to_string(), Value::Integer(stats.runs as i64));
+            table.insert("seconds".to_string(), Value::Integer(stats.seconds as i64));
+            table.insert(
+                "corpus_size".to_string(),
+                Value::Integer(stats.corpus_size as i64),
+            );
+            table.insert(
+                "last_growth".to_string(),
+                Value::Integer(stats.last_growth as i64),
+            );
+            table.insert(
+                "stale_runs".to_string(),
+                Value::Integer(stats.stale_runs as i64),
+            );
+            targets.insert(name.clone(), Value::Table(table));
+        }
+        let mut root = Table::new();
+        root.insert("targets".to_string(), Value::Table(targets));
+
+        let contents = toml::to_string(&Value::Table(root))
+            .map_err(|error| format!("Failed to serialize campaign statistics: {}", error))?;
+        fs::write(&self.path, contents)
+            .map_err(|error| format!("Failed to write {}: {}", self.path.display(), error))
+    }
+
+    pub fn get(&self, name: &str) -> TargetStats {
+        self.targets.get(name).cloned().unwrap_or_default()
+    }
+
+    /// Records a slice of `seconds`, after which the corpus of the target has `corpus_size` entries.
+    pub fn record(&mut self, name: &str, seconds: u64, corpus_size: u64) {
+        let stats = self.targets.entry(name.to_string()).or_default();
+        stats.runs += 1;
+        stats.seconds += seconds;
+        stats.last_growth = corpus_size.saturating_sub(stats.corpus_size);
+        stats.stale_runs = if stats.last_growth == 0 {
+            stats.stale_runs + 1
+        } else {
+            0
+        };
+        stats.corpus_size = corpus_size;
+    }
+}
+
+/// Orders targets for the given round: never fuzzed ones go first, then ones with the fastest
+/// growing corpus. Stale targets are left out, unless it is their turn.
+pub fn schedule<'a>(
+    targets: &[&'a Target],
+    stats: impl Fn(&Target) -> TargetStats,
+    round: u64,
+) -> Vec<&'a Target> {
+    let mut scheduled: Vec<(&Target, TargetStats)> = targets
+        .iter()
+        .map(|&target| (target, stats(target)))
+        .filter(|(_, stats)| !stats.is_stale() || round % STALE_ROUND_PERIOD == 0)
+        .collect();
+    // Sorting is stable, so targets with equal priority keep their round-robin order
+    scheduled.sort_by_key(|(_, stats)| (stats.runs != 0, std::cmp::Reverse(stats.last_growth)));
+    scheduled.into_iter().map(|(target, _)| target).collect()
+}
+
+pub fn corpus_size(target: &Target, crate_info: &CrateInfo) -> u64 {
+    fs::read_dir(target.corpus_dir(crate_info))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_file())
+                .count() as u64
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crate_parse::Backend;
+    use tempfile::tempdir;
+
+    fn target(name: &str) -> Target {
+        Target {
+            name: name.to_string(),
+            backend: Backend::LibFuzzer,
+        }
+    }
+
+    #[test]
+    fn record_and_persist() {
+        let dir = tempdir().unwrap();
+        let mut stats = Stats::load(dir.path()).unwrap();
+        assert_eq!(stats.get("foo"), TargetStats::default());
+
+        stats.record("foo", 60, 10);
+        stats.record("foo", 60, 10);
+        assert_eq!(
+            stats.get("foo"),
+            TargetStats {
+                runs: 2,
+                seconds: 120,
+                corpus_size: 10,
+                last_growth: 0,
+                stale_runs: 1,
+            }
+        );
+        stats.record("bar", 30, 4);
+        stats.save().unwrap();
+
+        assert_eq!(Stats::load(dir.path()).unwrap(), stats);
+    }
+
+    #[test]
+    fn priorities() {
+        let (new, growing, slow, stale) = (
+            target("new"),
+            target("growing"),
+            target("slow"),
+            target("stale"),
+        );
+        let targets = [&stale, &slow, &growing, &new];
+        let stats = |target: &Target| match target.name.as_str() {
+            "growing" => TargetStats {
+                runs: 1,
+                last_growth: 20,
+                ..TargetStats::default()
+            },
+            "slow" => TargetStats {
+                runs: 1,
+                last_growth: 1,
+                ..TargetStats::default()
+            },
+            "stale" => TargetStats {
+                runs: 5,
+                stale_runs: STALE_AFTER,
+                ..TargetStats::default()
+            },
+            _ => TargetStats::default(),
+        };
+
+        let names = |scheduled: Vec<&Target>| -> Vec<String> {
+            scheduled.iter().map(|target| target.name.clone()).collect()
+        };
+        assert_eq!(
+            names(schedule(&targets, stats, 1)),
+            ["new", "growing", "slow"]
+        );
+        assert_eq!(
+            names(schedule(&targets, stats, STALE_ROUND_PERIOD)),
+            ["new", "growing", "slow", "stale"]
+        );
+    }
+}
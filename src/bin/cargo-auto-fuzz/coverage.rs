@@ -0,0 +1,334 @@
+use crate::crate_parse::{Backend, CrateInfo};
+use crate::target::Target;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Sources outside of the fuzzed crate, which are left out of reports.
+const IGNORED_SOURCES: &str = r"/\.cargo/registry/|^/rustc/";
+
+/// `llvm-profdata` and `llvm-cov`, preferably from the `llvm-tools-preview` component, whose
+/// LLVM version matches the one of rustc.
+pub struct Tools {
+    profdata: PathBuf,
+    cov: PathBuf,
+}
+
+impl Tools {
+    pub fn find() -> Tools {
+        let rustc = |args: &[&str]| {
+            Command::new("rustc")
+                .args(args)
+                .output()
+                .ok()
+                .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+        };
+        let sysroot_bin = rustc(&["--print", "sysroot"]).and_then(|sysroot| {
+            let host = rustc(&["-vV"])?
+                .lines()
+                .find_map(|line| line.strip_prefix("host: "))?
+                .to_string();
+            Some(
+                Path::new(sysroot.trim())
+                    .join("lib/rustlib")
+                    .join(host)
+                    .join("bin"),
+            )
+        });
+        let tool = |name: &str| match &sysroot_bin {
+            Some(bin) if bin.join(name).is_file() => bin.join(name),
+            _ => PathBuf::from(name),
+        };
+        Tools {
+            profdata: tool("llvm-profdata"),
+            cov: tool("llvm-cov"),
+        }
+    }
+}
+
+/// Coverage data of a single target.
+pub struct TargetCoverage {
+    pub target: String,
+    /// Report of `llvm-cov export`, each record is annotated with the target name.
+    pub lcov: String,
+}
+
+impl TargetCoverage {
+    pub fn lines(&self) -> (u64, u64) {
+        summary(&self.lcov, "LH:", "LF:")
+    }
+
+    pub fn functions(&self) -> (u64, u64) {
+        summary(&self.lcov, "FNH:", "FNF:")
+    }
+}
+
+pub fn coverage_dir(crate_info: &CrateInfo) -> PathBuf {
+    crate_info
+        .with_backend(Backend::LibFuzzer)
+        .fuzz_crate_dir()
+        .join("coverage")
+}
+
+/// Corpora are replayed by the generated `replay_corpus` test, built with instrumentation into a
+/// separate target dir, so regular builds are not invalidated.
+fn replay_command(crate_info: &CrateInfo) -> Command {
+    let fuzz_crate_dir = crate_info.with_backend(Backend::LibFuzzer).fuzz_crate_dir();
+    let mut rustflags = env::var("RUSTFLAGS").unwrap_or_default();
+    rustflags.push_str(" --cfg fuzzing -C instrument-coverage");
+
+    let mut command = Command::new("cargo");
+    command
+        .current_dir(&fuzz_crate_dir)
+        .env("RUSTFLAGS", rustflags.trim())
+        .env(
+            "CARGO_TARGET_DIR",
+            fuzz_crate_dir.join("target").join("coverage"),
+        )
+        .args(["test", "--test", "replay_corpus"]);
+    command
+}
+
+/// Builds the instrumented replay test and returns the path of its executable.
+pub fn build(crate_info: &CrateInfo) -> Result<PathBuf, String> {
+    let output = replay_command(crate_info)
+        .args(["--no-run", "--message-format=json"])
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|error| format!("Failed to run cargo: {}", error))?;
+    if !output.status.success() {
+        return Err("Failed to build the corpus replay test with coverage".to_string());
+    }
+    executable(&String::from_utf8_lossy(&output.stdout))
+        .map(PathBuf::from)
+        .ok_or_else(|| "Cargo did not report the corpus replay executable".to_string())
+}
+
+/// Finds the test executable in cargo's JSON messages, only the test itself has one.
+fn executable(messages: &str) -> Option<String> {
+    messages
+        .lines()
+        .filter_map(|line| line.split_once("\"executable\":\"")?.1.split_once('"'))
+        .map(|(path, _)| path.replace("\\\\", "\\"))
+        .next_back()
+}
+
+/// Replays the corpus of the target and turns the collected profile into an lcov report.
+pub fn collect(
+    target: &Target,
+    crate_info: &CrateInfo,
+    tools: &Tools,
+    executable: &Path,
+) -> Result<TargetCoverage, String> {
+    let profile_dir = coverage_dir(crate_info).join("profiles").join(&target.name);
+    // Stale profiles would be merged into the new report
+    let _ = fs::remove_dir_all(&profile_dir);
+    fs::create_dir_all(&profile_dir)
+        .map_err(|error| format!("Failed to create {}: {}", profile_dir.display(), error))?;
+
+    let status = replay_command(crate_info)
+        .args(["--", "--exact", "replay_corpus"])
+        .env("AUTO_FUZZ_REPLAY_TARGET", &target.name)
+        .env("LLVM_PROFILE_FILE", profile_dir.join("%p-%m.profraw"))
+        .stdout(Stdio::null())
+        .status()
+        .map_err(|error| format!("Failed to run cargo: {}", error))?;
+    if !status.success() {
+        // Coverage up to the crash is still recorded
+        eprintln!(
+            "warning: corpus of {} contains crashing inputs",
+            target.name
+        );
+    }
+
+    let profiles: Vec<PathBuf> = fs::read_dir(&profile_dir)
+        .map_err(|error| format!("Failed to read {}: {}", profile_dir.display(), error))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "profraw"))
+        .collect();
+    if profiles.is_empty() {
+        return Err(format!(
+            "No coverage profile was written for {}",
+            target.name
+        ));
+    }
+    let profdata = coverage_dir(crate_info).join(format!("{}.profdata", target.name));
+    run_tool(
+        Command::new(&tools.profdata)
+            .args(["merge", "-sparse", "-o"])
+            .arg(&profdata)
+            .args(&profiles),
+    )?;
+
+    let cov = |format: &str| {
+        let mut command = Command::new(&tools.cov);
+        command
+            .arg(format)
+            .arg(format!("-instr-profile={}", profdata.display()))
+            .arg(format!("-ignore-filename-regex={}", IGNORED_SOURCES))
+            .arg(executable);
+        command
+    };
+    let html_dir = coverage_dir(crate_info).join("html").join(&target.name);
+    run_tool(
+        cov("show")
+            .args(["-format=html", "-show-line-counts-or-regions"])
+            .arg(format!("-output-dir={}", html_dir.display())),
+    )?;
+    let lcov = run_tool(cov("export").arg("-format=lcov"))?;
+
+    Ok(TargetCoverage {
+        target: target.name.clone(),
+        lcov: annotate_lcov(&lcov, &target.name),
+    })
+}
+
+fn run_tool(command: &mut Command) -> Result<String, String> {
+    let output = command.stderr(Stdio::inherit()).output().map_err(|error| {
+        format!(
+            "Failed to run {:?}: {}, try `rustup component add llvm-tools-preview`",
+            command.get_program(),
+            error
+        )
+    })?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(format!(
+            "{:?} failed, its LLVM version has to match the one of rustc, try `rustup component add llvm-tools-preview`",
+            command.get_program()
+        ))
+    }
+}
+
+/// Names every record with the target, lcov calls it a test name.
+fn annotate_lcov(lcov: &str, target: &str) -> String {
+    let mut annotated = String::new();
+    for line in lcov.lines().filter(|line| !line.starts_with("TN:")) {
+        if line.starts_with("SF:") {
+            writeln!(annotated, "TN:{}", target).unwrap();
+        }
+        annotated.push_str(line);
+        annotated.push('\n');
+    }
+    annotated
+}
+
+/// Sums up `hit` and `found` counters of all records.
+fn summary(lcov: &str, hit: &str, found: &str) -> (u64, u64) {
+    let sum = |prefix: &str| -> u64 {
+        lcov.lines()
+            .filter_map(|line| line.strip_prefix(prefix)?.trim().parse::<u64>().ok())
+            .sum()
+    };
+    (sum(hit), sum(found))
+}
+
+fn percent((hit, found): (u64, u64)) -> String {
+    if found == 0 {
+        "-".to_string()
+    } else {
+        format!("{:.1}%", hit as f64 * 100.0 / found as f64)
+    }
+}
+
+/// Summary table, which links reports of every target.
+pub fn index_html(report: &[TargetCoverage]) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Fuzzing coverage</title></head>\n<body>\n<h1>Fuzzing coverage</h1>\n<table>\n<tr><th>Target</th><th>Lines</th><th>Functions</th></tr>\n",
+    );
+    for coverage in report {
+        writeln!(
+            html,
+            "<tr><td><a href=\"html/{0}/index.html\">{0}</a></td><td>{1}</td><td>{2}</td></tr>",
+            coverage.target,
+            percent(coverage.lines()),
+            percent(coverage.functions())
+        )
+        .unwrap();
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+pub fn summary_table(report: &[TargetCoverage]) -> String {
+    let width = report
+        .iter()
+        .map(|coverage| coverage.target.len())
+        .max()
+        .unwrap_or(0)
+        .max("target".len());
+    let mut table = format!("{:width$}  {:>9}  {:>9}\n", "target", "lines", "functions");
+    for coverage in report {
+        writeln!(
+            table,
+            "{:width$}  {:>9}  {:>9}",
+            coverage.target,
+            percent(coverage.lines()),
+            percent(coverage.functions())
+        )
+        .unwrap();
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const LCOV: &str = "SF:/crate/src/lib.rs
+FN:3,_RNvCs1_8test_lib12bool_to_num
+FNDA:4,_RNvCs1_8test_lib12bool_to_num
+FNF:2
+FNH:1
+DA:3,4
+DA:4,0
+LF:4
+LH:3
+end_of_record
+SF:/crate/src/module.rs
+FNF:1
+FNH:0
+LF:6
+LH:0
+end_of_record
+";
+
+    #[test]
+    fn annotate() {
+        let coverage = TargetCoverage {
+            target: "bool_to_num".to_string(),
+            lcov: annotate_lcov(LCOV, "bool_to_num"),
+        };
+        assert!(coverage
+            .lcov
+            .starts_with("TN:bool_to_num\nSF:/crate/src/lib.rs\n"));
+        assert!(coverage
+            .lcov
+            .contains("end_of_record\nTN:bool_to_num\nSF:/crate/src/module.rs\n"));
+        assert_eq!(coverage.lines(), (3, 10));
+        assert_eq!(coverage.functions(), (1, 3));
+
+        assert_eq!(
+            summary_table(&[coverage]),
+            "target           lines  functions\nbool_to_num      30.0%      33.3%\n"
+        );
+        assert_eq!(percent((0, 0)), "-");
+    }
+
+    #[test]
+    fn test_executable() {
+        let messages = r#"{"reason":"compiler-artifact","target":{"name":"test_lib"},"executable":null}
+{"reason":"compiler-artifact","target":{"name":"replay_corpus"},"executable":"/fuzz/target/coverage/debug/deps/replay_corpus-0123"}
+{"reason":"build-finished","success":true}"#;
+        assert_eq!(
+            executable(messages).as_deref(),
+            Some("/fuzz/target/coverage/debug/deps/replay_corpus-0123")
+        );
+        assert_eq!(executable("{\"executable\":null}"), None);
+    }
+}
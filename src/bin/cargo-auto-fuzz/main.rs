@@ -0,0 +1,251 @@
+//! `cargo auto-fuzz` lists, builds and runs fuzzing targets generated by `auto-fuzz-test`.
+
+#[allow(dead_code)]
+#[path = "../../crate_parse.rs"]
+mod crate_parse;
+
+mod args;
+mod campaign;
+mod coverage;
+#[path = "../../glob.rs"]
+mod glob;
+mod target;
+mod triage;
+
+use args::Command;
+use crate_parse::CrateInfo;
+use std::collections::hash_map::{Entry, HashMap};
+use std::env;
+use std::fs;
+use std::process;
+use target::Target;
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // Cargo passes the subcommand name as the first argument
+    if args.first().map(String::as_str) == Some("auto-fuzz") {
+        args.remove(0);
+    }
+
+    if let Err(error) = run(&args) {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let command = Command::parse(args)?;
+    if command == Command::Help {
+        println!("{}", args::USAGE);
+        return Ok(());
+    }
+
+    let current_dir =
+        env::current_dir().map_err(|error| format!("Failed to obtain current dir: {}", error))?;
+    let crate_info = CrateInfo::from_root(&current_dir)?;
+    let targets = target::discover(&crate_info)?;
+
+    match command {
+        Command::Help => unreachable!(),
+        Command::List => {
+            for target in &targets {
+                println!("{} ({})", target.name, target.backend.name());
+            }
+            Ok(())
+        }
+        Command::Build { pattern } => {
+            let pattern = pattern.as_deref().unwrap_or("*");
+            let failed = for_each_matching(&targets, pattern, |target| target.build(&crate_info))?;
+            report(failed, "Failed to build")
+        }
+        Command::Run {
+            pattern,
+            fuzzer_args,
+        } => {
+            let failed = for_each_matching(&targets, &pattern, |target| {
+                target.run(&crate_info, None, &fuzzer_args)
+            })?;
+            report(failed, "Fuzzing failed for")
+        }
+        Command::RunAll { time_per_target } => {
+            let failed = for_each_matching(&targets, "*", |target| {
+                target.run(&crate_info, Some(time_per_target), &[])
+            })?;
+            report(failed, "Fuzzing failed for")
+        }
+        Command::Campaign {
+            pattern,
+            slice,
+            rounds,
+        } => {
+            let pattern = pattern.as_deref().unwrap_or("*");
+            let mut matching: Vec<&Target> = targets
+                .iter()
+                .filter(|target| glob::matches(pattern, &target.name))
+                .collect();
+            if matching.is_empty() {
+                return Err(format!("No targets match `{}`", pattern));
+            }
+            let mut stats = HashMap::new();
+            for target in &matching {
+                if let Entry::Vacant(entry) = stats.entry(target.backend) {
+                    let fuzz_crate_dir = crate_info.with_backend(target.backend).fuzz_crate_dir();
+                    entry.insert(campaign::Stats::load(&fuzz_crate_dir)?);
+                }
+            }
+
+            let mut failed = Vec::new();
+            let mut round = 0;
+            while rounds.map_or(true, |rounds| round < rounds) && !matching.is_empty() {
+                let scheduled = campaign::schedule(
+                    &matching,
+                    |target| stats[&target.backend].get(&target.name),
+                    round,
+                );
+                for target in scheduled {
+                    eprintln!(
+                        "==> {} ({}), round {}",
+                        target.name,
+                        target.backend.name(),
+                        round + 1
+                    );
+                    let success = target.run(&crate_info, Some(slice), &[])?;
+                    let target_stats = stats.get_mut(&target.backend).unwrap();
+                    target_stats.record(
+                        &target.name,
+                        slice,
+                        campaign::corpus_size(target, &crate_info),
+                    );
+                    target_stats.save()?;
+                    if !success {
+                        // Crashing targets would only keep reporting the same crash
+                        failed.push(target.name.as_str());
+                        matching.retain(|other| other != &target);
+                    }
+                }
+                round += 1;
+            }
+            report(failed, "Fuzzing failed for")
+        }
+        Command::Triage { pattern } => {
+            let matching = cargo_fuzz_targets(&targets, pattern.as_deref())?;
+            triage::build_replay(&crate_info)?;
+
+            let mut report = Vec::new();
+            for target in matching {
+                let artifacts = triage::artifacts(target, &crate_info);
+                if artifacts.is_empty() {
+                    continue;
+                }
+                eprintln!("==> {} ({} crashes)", target.name, artifacts.len());
+                let crashes = artifacts
+                    .iter()
+                    .map(|artifact| triage::replay(target, &crate_info, artifact))
+                    .collect::<Result<Vec<_>, _>>()?;
+                report.push((target.name.clone(), triage::bucket(crashes)));
+            }
+
+            let fuzz_crate_dir = crate_info
+                .with_backend(crate_parse::Backend::LibFuzzer)
+                .fuzz_crate_dir();
+            for (file, contents) in [
+                ("triage.md", triage::markdown(&report)),
+                ("triage.json", triage::json(&report)),
+            ] {
+                let path = fuzz_crate_dir.join(file);
+                fs::write(&path, contents)
+                    .map_err(|error| format!("Failed to write {}: {}", path.display(), error))?;
+            }
+            let unique: usize = report.iter().map(|(_, buckets)| buckets.len()).sum();
+            println!(
+                "{} unique crashes in {} targets, see {}",
+                unique,
+                report.len(),
+                fuzz_crate_dir.join("triage.md").display()
+            );
+            Ok(())
+        }
+        Command::Coverage { pattern } => {
+            let matching = cargo_fuzz_targets(&targets, pattern.as_deref())?;
+            let tools = coverage::Tools::find();
+            let executable = coverage::build(&crate_info)?;
+
+            let mut report = Vec::new();
+            for target in matching {
+                eprintln!("==> {}", target.name);
+                report.push(coverage::collect(target, &crate_info, &tools, &executable)?);
+            }
+
+            let coverage_dir = coverage::coverage_dir(&crate_info);
+            let lcov: String = report
+                .iter()
+                .map(|coverage| coverage.lcov.as_str())
+                .collect();
+            for (file, contents) in [
+                ("lcov.info", lcov),
+                ("index.html", coverage::index_html(&report)),
+            ] {
+                let path = coverage_dir.join(file);
+                fs::write(&path, contents)
+                    .map_err(|error| format!("Failed to write {}: {}", path.display(), error))?;
+            }
+            print!("{}", coverage::summary_table(&report));
+            println!("See {}", coverage_dir.join("index.html").display());
+            Ok(())
+        }
+    }
+}
+
+/// Targets of cargo-fuzz matching `pattern`, replaying inputs relies on the tests generated
+/// only for it.
+fn cargo_fuzz_targets<'a>(
+    targets: &'a [Target],
+    pattern: Option<&str>,
+) -> Result<Vec<&'a Target>, String> {
+    let pattern = pattern.unwrap_or("*");
+    let matching: Vec<&Target> = targets
+        .iter()
+        .filter(|target| {
+            target.backend == crate_parse::Backend::LibFuzzer
+                && glob::matches(pattern, &target.name)
+        })
+        .collect();
+    if matching.is_empty() {
+        Err(format!("No cargo-fuzz targets match `{}`", pattern))
+    } else {
+        Ok(matching)
+    }
+}
+
+/// Applies `action` to every target matching `pattern` and returns names of failed ones.
+fn for_each_matching<'a>(
+    targets: &'a [Target],
+    pattern: &str,
+    mut action: impl FnMut(&Target) -> Result<bool, String>,
+) -> Result<Vec<&'a str>, String> {
+    let mut matched = false;
+    let mut failed = Vec::new();
+    for target in targets
+        .iter()
+        .filter(|target| glob::matches(pattern, &target.name))
+    {
+        matched = true;
+        eprintln!("==> {} ({})", target.name, target.backend.name());
+        if !action(target)? {
+            failed.push(target.name.as_str());
+        }
+    }
+    if matched {
+        Ok(failed)
+    } else {
+        Err(format!("No targets match `{}`", pattern))
+    }
+}
+
+fn report(failed: Vec<&str>, message: &str) -> Result<(), String> {
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{}: {}", message, failed.join(", ")))
+    }
+}
@@ -0,0 +1,364 @@
+use crate::crate_parse::{Backend, CrateInfo};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Fuzzing target, which is listed in the Cargo.toml of one of the fuzzing crates.
+#[derive(Debug, PartialEq)]
+pub struct Target {
+    pub name: String,
+    pub backend: Backend,
+}
+
+/// Collects targets of every backend, which has a fuzzing crate in the user's crate.
+pub fn discover(crate_info: &CrateInfo) -> Result<Vec<Target>, String> {
+    let mut targets = Vec::new();
+    for &backend in Backend::ALL.iter() {
+        let backend_info = crate_info.with_backend(backend);
+        if !backend_info.fuzz_crate_dir().join("Cargo.toml").is_file() {
+            continue;
+        }
+        let names = backend_info.fuzz_targets().map_err(|error| {
+            format!(
+                "Failed to read targets from {}: {}",
+                backend_info.fuzz_crate_dir().display(),
+                error
+            )
+        })?;
+        targets.extend(names.into_iter().map(|name| Target { name, backend }));
+    }
+    Ok(targets)
+}
+
+impl Target {
+    pub fn build_command(&self, crate_info: &CrateInfo) -> Command {
+        let crate_info = crate_info.with_backend(self.backend);
+        let mut command = Command::new("cargo");
+        match self.backend {
+            Backend::LibFuzzer => {
+                command
+                    .current_dir(crate_info.crate_root())
+                    .args(["fuzz", "build", &self.name]);
+            }
+            Backend::Honggfuzz => {
+                command
+                    .current_dir(crate_info.fuzz_crate_dir())
+                    .args(["hfuzz", "build", "--bin", &self.name]);
+            }
+            Backend::Afl => {
+                command
+                    .current_dir(crate_info.fuzz_crate_dir())
+                    .args(["afl", "build", "--bin", &self.name]);
+            }
+        }
+        command
+    }
+
+    /// Command, which fuzzes the target for `time` seconds, or until a crash if there is no limit.
+    pub fn run_command(
+        &self,
+        crate_info: &CrateInfo,
+        time: Option<u64>,
+        fuzzer_args: &[String],
+    ) -> Command {
+        let crate_info = crate_info.with_backend(self.backend);
+        let mut command = Command::new("cargo");
+        match self.backend {
+            Backend::LibFuzzer => {
+                command
+                    .current_dir(crate_info.crate_root())
+                    .args(["fuzz", "run", &self.name, "--"]);
+                if let Some(time) = time {
+                    command.arg(format!("-max_total_time={}", time));
+                }
+                if let Some(dictionary) = self.dictionary(&crate_info) {
+                    command.arg(format!("-dict={}", dictionary.display()));
+                }
+                command.args(fuzzer_args);
+            }
+            Backend::Honggfuzz => {
+                let mut run_args = Vec::new();
+                if let Some(time) = time {
+                    run_args.push(format!("--run_time {}", time));
+                }
+                if let Some(dictionary) = self.dictionary(&crate_info) {
+                    run_args.push(format!("--dict {}", dictionary.display()));
+                }
+                run_args.extend(fuzzer_args.iter().cloned());
+                command
+                    .current_dir(crate_info.fuzz_crate_dir())
+                    .env("HFUZZ_RUN_ARGS", run_args.join(" "))
+                    .args(["hfuzz", "run", &self.name]);
+            }
+            Backend::Afl => {
+                // Fresh inputs would wipe the queue of a previous run, so AFL resumes from it
+                let inputs = if self.corpus_dir(&crate_info).is_dir() {
+                    "-".to_string()
+                } else {
+                    format!("in/{}", self.name)
+                };
+                command
+                    .current_dir(crate_info.fuzz_crate_dir())
+                    .args(["afl", "fuzz", "-i"])
+                    .arg(inputs)
+                    .arg("-o")
+                    .arg(format!("out/{}", self.name));
+                if let Some(time) = time {
+                    command.arg("-V").arg(time.to_string());
+                }
+                if let Some(dictionary) = self.dictionary(&crate_info) {
+                    command.arg("-x").arg(dictionary);
+                }
+                command
+                    .args(fuzzer_args)
+                    .arg(format!("target/debug/{}", self.name));
+            }
+        }
+        command
+    }
+
+    /// Dictionary of literals, which was generated along with the harness.
+    pub fn dictionary(&self, crate_info: &CrateInfo) -> Option<PathBuf> {
+        let dictionary = crate_info
+            .with_backend(self.backend)
+            .fuzz_crate_dir()
+            .join("fuzz_targets")
+            .join(format!("{}.dict", self.name));
+        if dictionary.is_file() {
+            Some(dictionary)
+        } else {
+            None
+        }
+    }
+
+    /// Directory, where the fuzzer keeps inputs, which reached new coverage.
+    pub fn corpus_dir(&self, crate_info: &CrateInfo) -> PathBuf {
+        let fuzz_crate_dir = crate_info.with_backend(self.backend).fuzz_crate_dir();
+        match self.backend {
+            Backend::LibFuzzer => fuzz_crate_dir.join("corpus").join(&self.name),
+            Backend::Honggfuzz => fuzz_crate_dir
+                .join("hfuzz_workspace")
+                .join(&self.name)
+                .join("input"),
+            Backend::Afl => fuzz_crate_dir
+                .join("out")
+                .join(&self.name)
+                .join("default")
+                .join("queue"),
+        }
+    }
+
+    pub fn build(&self, crate_info: &CrateInfo) -> Result<bool, String> {
+        status(&mut self.build_command(crate_info))
+    }
+
+    pub fn run(
+        &self,
+        crate_info: &CrateInfo,
+        time: Option<u64>,
+        fuzzer_args: &[String],
+    ) -> Result<bool, String> {
+        if self.backend == Backend::Afl {
+            // `cargo afl fuzz` neither builds the target, nor starts without initial inputs
+            if !self.build(crate_info)? {
+                return Ok(false);
+            }
+            let seeds = crate_info.with_backend(self.backend).seed_dir(&self.name);
+            let has_seeds = fs::read_dir(&seeds)
+                .map(|mut entries| entries.next().is_some())
+                .unwrap_or(false);
+            if !has_seeds {
+                fs::create_dir_all(&seeds)
+                    .and_then(|_| fs::write(seeds.join("empty"), b"\0"))
+                    .map_err(|error| format!("Failed to create AFL inputs: {}", error))?;
+            }
+        }
+        status(&mut self.run_command(crate_info, time, fuzzer_args))
+    }
+}
+
+fn status(command: &mut Command) -> Result<bool, String> {
+    command
+        .status()
+        .map(|status| status.success())
+        .map_err(|error| format!("Failed to run {:?}: {}", command, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn crate_info(root: &std::path::Path) -> CrateInfo {
+        let mut cargo_toml = fs::File::create(root.join("Cargo.toml")).unwrap();
+        writeln!(
+            cargo_toml,
+            "[package]\nname = \"test-lib\"\nversion = \"0.1.0\""
+        )
+        .unwrap();
+        CrateInfo::from_root(root).unwrap()
+    }
+
+    fn args(command: &Command) -> Vec<String> {
+        command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn discover_targets() {
+        let dir = tempdir().unwrap();
+        let crate_info = crate_info(dir.path());
+        assert_eq!(discover(&crate_info), Ok(Vec::new()));
+
+        fs::create_dir(dir.path().join("fuzz")).unwrap();
+        fs::write(
+            dir.path().join("fuzz").join("Cargo.toml"),
+            "[[bin]]\nname = \"foo\"\n\n[[bin]]\nname = \"bar\"\n",
+        )
+        .unwrap();
+        fs::create_dir(dir.path().join("afl")).unwrap();
+        fs::write(
+            dir.path().join("afl").join("Cargo.toml"),
+            "[[bin]]\nname = \"foo\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            discover(&crate_info),
+            Ok(vec![
+                Target {
+                    name: "foo".to_string(),
+                    backend: Backend::LibFuzzer
+                },
+                Target {
+                    name: "bar".to_string(),
+                    backend: Backend::LibFuzzer
+                },
+                Target {
+                    name: "foo".to_string(),
+                    backend: Backend::Afl
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn commands() {
+        let dir = tempdir().unwrap();
+        let crate_info = crate_info(dir.path());
+        let fuzzer_args = vec!["-x".to_string()];
+
+        let target = Target {
+            name: "foo".to_string(),
+            backend: Backend::LibFuzzer,
+        };
+        assert_eq!(
+            args(&target.build_command(&crate_info)),
+            ["fuzz", "build", "foo"]
+        );
+        assert_eq!(
+            args(&target.run_command(&crate_info, Some(10), &fuzzer_args)),
+            ["fuzz", "run", "foo", "--", "-max_total_time=10", "-x"]
+        );
+
+        let target = Target {
+            name: "foo".to_string(),
+            backend: Backend::Honggfuzz,
+        };
+        let command = target.run_command(&crate_info, Some(10), &fuzzer_args);
+        assert_eq!(args(&command), ["hfuzz", "run", "foo"]);
+        assert_eq!(
+            command.get_current_dir(),
+            Some(dir.path().join("hfuzz").as_path())
+        );
+
+        let target = Target {
+            name: "foo".to_string(),
+            backend: Backend::Afl,
+        };
+        assert_eq!(
+            args(&target.run_command(&crate_info, None, &fuzzer_args)),
+            [
+                "afl",
+                "fuzz",
+                "-i",
+                "in/foo",
+                "-o",
+                "out/foo",
+                "-x",
+                "target/debug/foo"
+            ]
+        );
+
+        // Later runs resume from the queue of the previous one
+        fs::create_dir_all(target.corpus_dir(&crate_info)).unwrap();
+        assert_eq!(
+            args(&target.run_command(&crate_info, Some(10), &[])),
+            [
+                "afl",
+                "fuzz",
+                "-i",
+                "-",
+                "-o",
+                "out/foo",
+                "-V",
+                "10",
+                "target/debug/foo"
+            ]
+        );
+    }
+
+    #[test]
+    fn dictionary_commands() {
+        let dir = tempdir().unwrap();
+        let crate_info = crate_info(dir.path());
+        for backend in ["fuzz", "hfuzz"] {
+            let fuzz_targets = dir.path().join(backend).join("fuzz_targets");
+            fs::create_dir_all(&fuzz_targets).unwrap();
+            fs::write(fuzz_targets.join("foo.dict"), "\"GET\"\n").unwrap();
+        }
+
+        let target = Target {
+            name: "foo".to_string(),
+            backend: Backend::LibFuzzer,
+        };
+        let dictionary = dir.path().join("fuzz/fuzz_targets/foo.dict");
+        assert_eq!(target.dictionary(&crate_info), Some(dictionary.clone()));
+        assert_eq!(
+            args(&target.run_command(&crate_info, None, &[])),
+            [
+                "fuzz".to_string(),
+                "run".to_string(),
+                "foo".to_string(),
+                "--".to_string(),
+                format!("-dict={}", dictionary.display())
+            ]
+        );
+
+        let target = Target {
+            name: "foo".to_string(),
+            backend: Backend::Honggfuzz,
+        };
+        let command = target.run_command(&crate_info, Some(10), &[]);
+        let run_args = command
+            .get_envs()
+            .find(|(key, _)| *key == "HFUZZ_RUN_ARGS")
+            .and_then(|(_, value)| value)
+            .unwrap();
+        assert_eq!(
+            run_args.to_string_lossy(),
+            format!(
+                "--run_time 10 --dict {}",
+                dir.path().join("hfuzz/fuzz_targets/foo.dict").display()
+            )
+        );
+
+        let target = Target {
+            name: "foo".to_string(),
+            backend: Backend::Afl,
+        };
+        assert_eq!(target.dictionary(&crate_info), None);
+    }
+}
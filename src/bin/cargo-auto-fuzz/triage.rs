@@ -0,0 +1,418 @@
+use crate::crate_parse::{Backend, CrateInfo};
+use crate::target::Target;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Number of stack frames, which make up a crash signature along with the panic location.
+const SIGNATURE_FRAMES: usize = 3;
+
+/// Outcome of replaying a single input.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Replay {
+    pub message: Option<String>,
+    pub location: Option<String>,
+    /// Topmost frames of the crate under test, the panic machinery is left out.
+    pub frames: Vec<String>,
+    /// `Debug` rendering of the fuzz struct, which the input decodes to.
+    pub input: Option<String>,
+}
+
+impl Replay {
+    pub fn signature(&self) -> String {
+        match &self.location {
+            Some(location) => {
+                let mut signature = location.clone();
+                for frame in &self.frames {
+                    signature.push_str(" <- ");
+                    signature.push_str(frame);
+                }
+                signature
+            }
+            None => "no panic".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Crash {
+    pub artifact: PathBuf,
+    pub size: u64,
+    pub replay: Replay,
+}
+
+/// Crashes with the same signature, smallest artifact first.
+#[derive(Debug, PartialEq)]
+pub struct Bucket {
+    pub signature: String,
+    pub crashes: Vec<Crash>,
+}
+
+impl Bucket {
+    pub fn minimal(&self) -> &Crash {
+        &self.crashes[0]
+    }
+}
+
+/// Crash artifacts of the target, which are found by cargo-fuzz.
+pub fn artifacts(target: &Target, crate_info: &CrateInfo) -> Vec<PathBuf> {
+    let dir = crate_info
+        .with_backend(target.backend)
+        .fuzz_crate_dir()
+        .join("artifacts")
+        .join(&target.name);
+    let mut artifacts: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.is_file()
+                        && path
+                            .file_name()
+                            .map(|name| name.to_string_lossy().starts_with("crash-"))
+                            .unwrap_or(false)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    artifacts.sort();
+    artifacts
+}
+
+/// Inputs are replayed by the generated `replay_corpus` test of the libFuzzer crate.
+fn replay_command(crate_info: &CrateInfo) -> Command {
+    let mut rustflags = env::var("RUSTFLAGS").unwrap_or_default();
+    rustflags.push_str(" --cfg fuzzing");
+
+    let mut command = Command::new("cargo");
+    command
+        .current_dir(crate_info.with_backend(Backend::LibFuzzer).fuzz_crate_dir())
+        .env("RUSTFLAGS", rustflags.trim())
+        .env("RUST_BACKTRACE", "1")
+        .args(["test", "--test", "replay_corpus"]);
+    command
+}
+
+pub fn build_replay(crate_info: &CrateInfo) -> Result<(), String> {
+    let status = replay_command(crate_info)
+        .arg("--no-run")
+        .status()
+        .map_err(|error| format!("Failed to run cargo: {}", error))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err("Failed to build the corpus replay test".to_string())
+    }
+}
+
+pub fn replay(target: &Target, crate_info: &CrateInfo, artifact: &Path) -> Result<Crash, String> {
+    let output = replay_command(crate_info)
+        .args(["--", "--ignored", "--exact", "replay_input", "--nocapture"])
+        .env("AUTO_FUZZ_REPLAY_TARGET", &target.name)
+        .env("AUTO_FUZZ_REPLAY_INPUT", artifact)
+        .output()
+        .map_err(|error| format!("Failed to run cargo: {}", error))?;
+    let size = fs::metadata(artifact)
+        .map_err(|error| format!("Failed to read {}: {}", artifact.display(), error))?
+        .len();
+
+    Ok(Crash {
+        artifact: artifact.to_path_buf(),
+        size,
+        replay: parse_replay(
+            &String::from_utf8_lossy(&output.stdout),
+            &String::from_utf8_lossy(&output.stderr),
+        ),
+    })
+}
+
+pub fn parse_replay(stdout: &str, stderr: &str) -> Replay {
+    let mut replay = Replay {
+        input: stdout
+            .lines()
+            // The test harness prints the test name on the same line
+            .find_map(|line| line.split_once("AUTO_FUZZ_INPUT: "))
+            .map(|(_, input)| input.to_string()),
+        ..Replay::default()
+    };
+
+    let mut lines = stderr.lines();
+    while let Some(line) = lines.next() {
+        let panic = match line.split_once(" panicked at ") {
+            Some((_, panic)) => panic,
+            None => continue,
+        };
+        if let Some(panic) = panic.strip_prefix('\'') {
+            // Before Rust 1.73: thread 'main' panicked at 'message', src/main.rs:2:5
+            if let Some((message, location)) = panic.rsplit_once("', ") {
+                replay.message = Some(message.to_string());
+                replay.location = Some(location.to_string());
+            }
+        } else {
+            // thread 'main' panicked at src/main.rs:2:5:
+            // message
+            replay.location = Some(panic.trim_end_matches(':').to_string());
+            let message: Vec<&str> = lines
+                .clone()
+                .take_while(|line| {
+                    !line.starts_with("stack backtrace:") && !line.starts_with("note: ")
+                })
+                .collect();
+            replay.message = Some(message.join("\n"));
+        }
+        break;
+    }
+
+    let frames = lines
+        .skip_while(|line| !line.starts_with("stack backtrace:"))
+        .filter_map(|line| {
+            let (number, frame) = line.trim_start().split_once(": ")?;
+            number.parse::<usize>().ok()?;
+            Some(strip_hash(frame))
+        });
+    for frame in frames {
+        // Everything below the generated functions belongs to the harness
+        if frame.contains("__fuzz_") || frame.contains("__auto_fuzz_") {
+            break;
+        }
+        let path = frame.trim_start_matches('<');
+        let is_runtime = ["std::", "core::", "alloc::", "rust_begin_unwind", "__rust"]
+            .iter()
+            .any(|prefix| path.starts_with(prefix));
+        if !is_runtime && replay.frames.len() < SIGNATURE_FRAMES {
+            replay.frames.push(frame.to_string());
+        }
+    }
+    replay
+}
+
+fn strip_hash(frame: &str) -> &str {
+    match frame.rsplit_once("::h") {
+        Some((path, hash)) if hash.len() == 16 && hash.chars().all(|c| c.is_ascii_hexdigit()) => {
+            path
+        }
+        _ => frame,
+    }
+}
+
+pub fn bucket(crashes: Vec<Crash>) -> Vec<Bucket> {
+    let mut buckets: Vec<Bucket> = Vec::new();
+    for crash in crashes {
+        let signature = crash.replay.signature();
+        match buckets
+            .iter_mut()
+            .find(|bucket| bucket.signature == signature)
+        {
+            Some(bucket) => bucket.crashes.push(crash),
+            None => buckets.push(Bucket {
+                signature,
+                crashes: vec![crash],
+            }),
+        }
+    }
+    for bucket in &mut buckets {
+        bucket
+            .crashes
+            .sort_by(|a, b| (a.size, &a.artifact).cmp(&(b.size, &b.artifact)));
+    }
+    buckets
+}
+
+pub fn markdown(report: &[(String, Vec<Bucket>)]) -> String {
+    let mut markdown = String::from("# Crash triage\n");
+    for (target, buckets) in report {
+        let crashes: usize = buckets.iter().map(|bucket| bucket.crashes.len()).sum();
+        writeln!(
+            markdown,
+            "\n## `{}`\n\n{} unique crashes out of {}.",
+            target,
+            buckets.len(),
+            crashes
+        )
+        .unwrap();
+        for (index, bucket) in buckets.iter().enumerate() {
+            let minimal = bucket.minimal();
+            let message = minimal.replay.message.as_deref().unwrap_or("no panic");
+            writeln!(
+                markdown,
+                "\n### {}. {}\n",
+                index + 1,
+                message.lines().next().unwrap_or("")
+            )
+            .unwrap();
+            if let Some(location) = &minimal.replay.location {
+                writeln!(markdown, "- Location: `{}`", location).unwrap();
+            }
+            for frame in &minimal.replay.frames {
+                writeln!(markdown, "- Frame: `{}`", frame).unwrap();
+            }
+            writeln!(markdown, "- Crashes: {}", bucket.crashes.len()).unwrap();
+            writeln!(
+                markdown,
+                "- Minimal input: `{}` ({} bytes)",
+                minimal.artifact.display(),
+                minimal.size
+            )
+            .unwrap();
+            if let Some(input) = &minimal.replay.input {
+                writeln!(markdown, "\n```text\n{}\n```", input).unwrap();
+            }
+        }
+    }
+    markdown
+}
+
+pub fn json(report: &[(String, Vec<Bucket>)]) -> String {
+    let optional = |value: &Option<String>| match value {
+        Some(value) => json_string(value),
+        None => "null".to_string(),
+    };
+    let targets: Vec<String> = report
+        .iter()
+        .map(|(target, buckets)| {
+            let buckets: Vec<String> = buckets
+                .iter()
+                .map(|bucket| {
+                    let minimal = bucket.minimal();
+                    let frames: Vec<String> =
+                        minimal.replay.frames.iter().map(|frame| json_string(frame)).collect();
+                    let artifacts: Vec<String> = bucket
+                        .crashes
+                        .iter()
+                        .map(|crash| json_string(&crash.artifact.display().to_string()))
+                        .collect();
+                    format!(
+                        "{{\"signature\": {}, \"message\": {}, \"location\": {}, \"frames\": [{}], \"minimal_input\": {}, \"minimal_input_size\": {}, \"input_debug\": {}, \"artifacts\": [{}]}}",
+                        json_string(&bucket.signature),
+                        optional(&minimal.replay.message),
+                        optional(&minimal.replay.location),
+                        frames.join(", "),
+                        json_string(&minimal.artifact.display().to_string()),
+                        minimal.size,
+                        optional(&minimal.replay.input),
+                        artifacts.join(", ")
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"target\": {}, \"crashes\": [{}]}}",
+                json_string(target),
+                buckets.join(", ")
+            )
+        })
+        .collect();
+    format!("{{\"targets\": [{}]}}\n", targets.join(", "))
+}
+
+fn json_string(value: &str) -> String {
+    let mut json = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const STDERR: &str = r#"    Finished `test` profile [unoptimized + debuginfo] target(s) in 0.05s
+     Running tests/replay_corpus.rs (target/debug/deps/replay_corpus-0123)
+
+thread 'replay_input' (1234) panicked at src/lib.rs:37:9:
+attempt to multiply with overflow
+stack backtrace:
+   0: rust_begin_unwind
+             at /rustc/library/std/src/panicking.rs:665:5
+   1: core::panicking::panic_const::panic_const_mul_overflow
+   2: test_lib::TestStruct::multiply::h0123456789abcdef
+             at ./src/lib.rs:37:9
+   3: <test_lib::Wrapper as test_lib::Run>::run
+   4: test_lib::__fuzz_TestStruct__multiply
+   5: test_lib::helper_below_harness
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.
+"#;
+
+    fn crash(artifact: &str, size: u64, location: &str) -> Crash {
+        Crash {
+            artifact: PathBuf::from(artifact),
+            size,
+            replay: Replay {
+                message: Some("boom".to_string()),
+                location: Some(location.to_string()),
+                frames: vec!["f".to_string()],
+                input: None,
+            },
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let replay = parse_replay(
+            "running 1 test\ntest replay_input ... AUTO_FUZZ_INPUT: Input { a: 1 }\nFAILED\n",
+            STDERR,
+        );
+        assert_eq!(
+            replay,
+            Replay {
+                message: Some("attempt to multiply with overflow".to_string()),
+                location: Some("src/lib.rs:37:9".to_string()),
+                frames: vec![
+                    "test_lib::TestStruct::multiply".to_string(),
+                    "<test_lib::Wrapper as test_lib::Run>::run".to_string(),
+                ],
+                input: Some("Input { a: 1 }".to_string()),
+            }
+        );
+        assert_eq!(
+            replay.signature(),
+            "src/lib.rs:37:9 <- test_lib::TestStruct::multiply <- <test_lib::Wrapper as test_lib::Run>::run"
+        );
+    }
+
+    #[test]
+    fn parse_old_format() {
+        let replay = parse_replay(
+            "",
+            "thread 'replay_input' panicked at 'Not a digit', src/private.rs:3:23\n",
+        );
+        assert_eq!(replay.message.as_deref(), Some("Not a digit"));
+        assert_eq!(replay.location.as_deref(), Some("src/private.rs:3:23"));
+        assert_eq!(parse_replay("", "").signature(), "no panic");
+    }
+
+    #[test]
+    fn buckets() {
+        let buckets = bucket(vec![
+            crash("crash-1", 10, "src/lib.rs:1:1"),
+            crash("crash-2", 3, "src/lib.rs:2:2"),
+            crash("crash-3", 5, "src/lib.rs:1:1"),
+        ]);
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].crashes.len(), 2);
+        assert_eq!(buckets[0].minimal().artifact, PathBuf::from("crash-3"));
+        assert_eq!(buckets[1].minimal().artifact, PathBuf::from("crash-2"));
+
+        let report = vec![("foo".to_string(), buckets)];
+        let markdown = markdown(&report);
+        assert!(markdown.contains("## `foo`\n\n2 unique crashes out of 3."));
+        assert!(markdown.contains("- Minimal input: `crash-3` (5 bytes)"));
+        assert!(json(&report).starts_with(
+            "{\"targets\": [{\"target\": \"foo\", \"crashes\": [{\"signature\": \"src/lib.rs:1:1 <- f\""
+        ));
+        assert_eq!(json_string("a\"b\\\n"), "\"a\\\"b\\\\\\n\"");
+    }
+}
@@ -1,55 +1,248 @@
 use fs3::FileExt;
-use proc_macro2::TokenStream;
+use proc_macro2::{TokenStream, TokenTree};
+use std::collections::BTreeMap;
 use std::fs::{File, OpenOptions};
-use std::io::{Error, ErrorKind, Read, Write};
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
-use syn::{Ident, Type};
+use std::str::FromStr;
+use std::sync::Mutex;
+use syn::{GenericArgument, Ident, Lit, Meta, MetaNameValue, NestedMeta, PathArguments, Type};
 
 use cargo_toml::Value::String as TomlString;
 use cargo_toml::Value::Table as TomlTable;
 use toml::value::Value as TomlValue;
 
+/// Fuzzing engine, which drives generated harnesses. Every backend gets its own fuzzing crate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Backend {
+    LibFuzzer,
+    Honggfuzz,
+    Afl,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 3] = [Backend::LibFuzzer, Backend::Honggfuzz, Backend::Afl];
+
+    /// Name of the backend, as it is written in attributes and Cargo.toml.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::LibFuzzer => "libfuzzer",
+            Backend::Honggfuzz => "honggfuzz",
+            Backend::Afl => "afl",
+        }
+    }
+
+    /// Directory of the fuzzing crate, relative to the crate root.
+    pub fn dir_name(&self) -> &'static str {
+        match self {
+            Backend::LibFuzzer => "fuzz",
+            Backend::Honggfuzz => "hfuzz",
+            Backend::Afl => "afl",
+        }
+    }
+
+    fn cargo_toml_infix(&self) -> &'static str {
+        match self {
+            Backend::LibFuzzer => Backend::LIBFUZZER_CARGO_TOML_TEMPLATE_INFIX,
+            Backend::Honggfuzz => Backend::HONGGFUZZ_CARGO_TOML_TEMPLATE_INFIX,
+            Backend::Afl => Backend::AFL_CARGO_TOML_TEMPLATE_INFIX,
+        }
+    }
+
+    const LIBFUZZER_CARGO_TOML_TEMPLATE_INFIX: &'static str = r#"-fuzz"
+version = "0.0.0"
+authors = ["Automatically generated"]
+publish = false
+edition = "2018"
+
+[package.metadata]
+cargo-fuzz = true
+
+[dependencies]
+libfuzzer-sys = "0.4"
+
+[dependencies."#;
+
+    const HONGGFUZZ_CARGO_TOML_TEMPLATE_INFIX: &'static str = r#"-hfuzz"
+version = "0.0.0"
+authors = ["Automatically generated"]
+publish = false
+edition = "2018"
+
+[dependencies]
+honggfuzz = "0.5"
+
+[dependencies."#;
+
+    const AFL_CARGO_TOML_TEMPLATE_INFIX: &'static str = r#"-afl"
+version = "0.0.0"
+authors = ["Automatically generated"]
+publish = false
+edition = "2018"
+
+[dependencies]
+afl = "0.15"
+
+[dependencies."#;
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Backend::ALL
+            .iter()
+            .copied()
+            .find(|backend| backend.name() == s)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown fuzzing backend `{}`, expected `libfuzzer`, `honggfuzz` or `afl`",
+                    s
+                )
+            })
+    }
+}
+
+/// Code, whose string, byte string and char literals make up the fuzzing dictionary of a target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DictionaryScope {
+    None,
+    Function,
+    Module,
+}
+
+impl FromStr for DictionaryScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(DictionaryScope::None),
+            "function" => Ok(DictionaryScope::Function),
+            "module" => Ok(DictionaryScope::Module),
+            _ => Err(format!(
+                "Unknown dictionary scope `{}`, expected `none`, `function` or `module`",
+                s
+            )),
+        }
+    }
+}
+
 #[derive(Clone)]
 #[cfg_attr(test, derive(Debug))]
 pub struct CrateInfo {
     crate_root: PathBuf,
     crate_name: String,
+    backend: Backend,
+    fuzz_cfg: String,
+    property_tests: bool,
+    seeds: bool,
+    dictionary: DictionaryScope,
+    executor: Option<String>,
+    setup: Option<String>,
+    arbitrary_crate: String,
+    arbitrary_feature: Option<String>,
+    cfg_features: Vec<String>,
 }
 
 impl CrateInfo {
-    pub fn from_root(path: &Path) -> Option<CrateInfo> {
-        if !path.is_dir() {
-            return None;
-        }
-        let mut entries = path.read_dir().ok()?;
-        let cargo_toml_present = entries.any(|result| {
-            result
-                .map(|entry| match entry.file_name().to_str() {
-                    Some(filename) => filename == "Cargo.toml",
-                    None => false,
-                })
-                .unwrap_or(false)
-        });
-        if cargo_toml_present {
-            if let Some(crate_name) = CrateInfo::parse_crate_name(&path.join("Cargo.toml")) {
-                Some(CrateInfo {
-                    crate_root: path.to_path_buf(),
-                    crate_name,
-                })
-            } else {
-                None
-            }
-        } else {
-            None
+    pub fn from_root(path: &Path) -> Result<CrateInfo, String> {
+        let cargo_toml_path = path.join("Cargo.toml");
+        if !cargo_toml_path.is_file() {
+            return Err(format!("No Cargo.toml was found in {}", path.display()));
         }
+        let cargo_toml = CrateInfo::read_cargo_toml(&cargo_toml_path)
+            .ok_or_else(|| format!("Failed to parse {}", cargo_toml_path.display()))?;
+        let crate_name = CrateInfo::parse_crate_name(&cargo_toml)
+            .ok_or_else(|| format!("No package name in {}", cargo_toml_path.display()))?;
+        // Invalid settings are reported, instead of being silently replaced with defaults
+        let invalid_metadata = |error: String| {
+            format!(
+                "Invalid [package.metadata.auto-fuzz-test] in {}: {}",
+                cargo_toml_path.display(),
+                error
+            )
+        };
+        let fuzz_cfg = CrateInfo::parse_fuzz_cfg(&cargo_toml).map_err(invalid_metadata)?;
+        Ok(CrateInfo {
+            crate_root: path.to_path_buf(),
+            crate_name,
+            backend: CrateInfo::parse_backend(&cargo_toml).map_err(invalid_metadata)?,
+            cfg_features: CrateInfo::parse_cfg_features(&cargo_toml, &fuzz_cfg)
+                .map_err(invalid_metadata)?,
+            fuzz_cfg,
+            property_tests: CrateInfo::parse_property_tests(&cargo_toml)
+                .map_err(invalid_metadata)?,
+            seeds: CrateInfo::parse_seeds(&cargo_toml).map_err(invalid_metadata)?,
+            dictionary: CrateInfo::parse_dictionary(&cargo_toml).map_err(invalid_metadata)?,
+            executor: CrateInfo::parse_executor(&cargo_toml).map_err(invalid_metadata)?,
+            setup: CrateInfo::parse_setup(&cargo_toml).map_err(invalid_metadata)?,
+            arbitrary_crate: CrateInfo::parse_arbitrary_crate(&cargo_toml),
+            arbitrary_feature: CrateInfo::parse_arbitrary_feature(&cargo_toml),
+        })
+    }
+
+    pub fn crate_root(&self) -> &Path {
+        &self.crate_root
     }
 
     pub fn crate_name(&self) -> &str {
         &self.crate_name
     }
 
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Same crate, but with harnesses written for another fuzzing backend.
+    pub fn with_backend(&self, backend: Backend) -> CrateInfo {
+        CrateInfo {
+            backend,
+            ..self.clone()
+        }
+    }
+
+    /// Configuration predicate, which guards generated fuzzing code in the user's crate.
+    pub fn fuzz_cfg(&self) -> &str {
+        &self.fuzz_cfg
+    }
+
+    /// Whether fuzzed functions are also checked by property tests in `cargo test`.
+    pub fn property_tests(&self) -> bool {
+        self.property_tests
+    }
+
+    /// Whether arguments of calls to fuzzed functions are recorded as seeds, when the crate is
+    /// built with `--cfg auto_fuzz_seeds`.
+    pub fn seeds(&self) -> bool {
+        self.seeds
+    }
+
+    pub fn dictionary(&self) -> DictionaryScope {
+        self.dictionary
+    }
+
+    /// Path of a `block_on` function, which drives futures of fuzzed async functions instead of
+    /// the built-in executor.
+    pub fn executor(&self) -> Option<&str> {
+        self.executor.as_deref()
+    }
+
+    /// Path of a hook, which prepares global state from the fuzzer's input before arguments of
+    /// fuzzed functions are decoded.
+    pub fn setup(&self) -> Option<&str> {
+        self.setup.as_deref()
+    }
+
+    /// Name, under which `arbitrary` is available in the user's crate.
+    pub fn arbitrary_crate(&self) -> &str {
+        &self.arbitrary_crate
+    }
+
     pub fn fuzz_dir(&self) -> std::io::Result<PathBuf> {
-        let fuzz_dir_path = self.crate_root.join("fuzz").join("fuzz_targets");
+        let fuzz_dir_path = self
+            .crate_root
+            .join(self.backend.dir_name())
+            .join("fuzz_targets");
         match std::fs::create_dir_all(&fuzz_dir_path) {
             Ok(_) => Ok(fuzz_dir_path),
             Err(e) => {
@@ -62,13 +255,57 @@ impl CrateInfo {
         }
     }
 
+    /// Root of the fuzzing crate of the current backend.
+    pub fn fuzz_crate_dir(&self) -> PathBuf {
+        self.crate_root.join(self.backend.dir_name())
+    }
+
+    /// Directory, which the fuzzer of the current backend reads initial inputs of the target from.
+    pub fn seed_dir(&self, target: &str) -> PathBuf {
+        let fuzz_crate_dir = self.fuzz_crate_dir();
+        match self.backend {
+            Backend::LibFuzzer => fuzz_crate_dir.join("corpus").join(target),
+            Backend::Honggfuzz => fuzz_crate_dir
+                .join("hfuzz_workspace")
+                .join(target)
+                .join("input"),
+            Backend::Afl => fuzz_crate_dir.join("in").join(target),
+        }
+    }
+
+    /// Directory of integration tests of the fuzzing crate.
+    pub fn tests_dir(&self) -> std::io::Result<PathBuf> {
+        let tests_dir_path = self.fuzz_crate_dir().join("tests");
+        std::fs::create_dir_all(&tests_dir_path)?;
+        Ok(tests_dir_path)
+    }
+
+    /// Names of all targets, which are listed in the fuzzing crate's Cargo.toml.
+    pub fn fuzz_targets(&self) -> std::io::Result<Vec<String>> {
+        let cargo_toml_path = self.fuzz_crate_dir().join("Cargo.toml");
+        let cargo_toml = CrateInfo::read_cargo_toml(&cargo_toml_path).ok_or_else(|| {
+            Error::new(ErrorKind::InvalidData, "Failed to parse fuzzing Cargo.toml")
+        })?;
+        Ok(cargo_toml
+            .get("bin")
+            .and_then(|bins| bins.as_array())
+            .map(|bins| {
+                bins.iter()
+                    .filter_map(|bin| bin.get("name")?.as_str())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
     pub fn add_target_to_cargo_toml(
         &self,
         function: &Ident,
         impl_type: Option<&Type>,
         module_path: &TokenStream,
     ) -> Result<(), Error> {
-        let ident = construct_harness_ident(function, impl_type, module_path);
+        let ident = construct_harness_ident(function, impl_type, module_path)
+            .map_err(|error| Error::new(ErrorKind::InvalidInput, error.to_string()))?;
 
         let cargo_toml_path = self.fuzz_dir()?.parent().unwrap().join("Cargo.toml");
         match OpenOptions::new()
@@ -81,11 +318,13 @@ impl CrateInfo {
 
                 write!(
                     file,
-                    "{}{}{}{}{}",
+                    "{}{}{}{}{}{}{}",
                     &CrateInfo::CARGO_TOML_TEMPLATE_PREFIX,
                     &self.crate_name(),
-                    &CrateInfo::CARGO_TOML_TEMPLATE_INFIX,
+                    &self.backend.cargo_toml_infix(),
                     &self.crate_name(),
+                    &CrateInfo::CARGO_TOML_TEMPLATE_PATH,
+                    self.fuzz_crate_features(),
                     &CrateInfo::CARGO_TOML_TEMPLATE_POSTFIX
                 )?;
 
@@ -100,14 +339,13 @@ impl CrateInfo {
                 )?;
                 file.flush()?;
 
-                file.unlock()?;
+                FileExt::unlock(&file)?;
                 Ok(())
             }
             Err(err) => {
                 if err.kind() == ErrorKind::AlreadyExists {
                     let mut file = OpenOptions::new()
                         .read(true)
-                        .write(true)
                         .append(true)
                         .open(&cargo_toml_path)?;
                     file.lock_exclusive()?;
@@ -116,25 +354,12 @@ impl CrateInfo {
                     let mut buffer = String::new();
                     file.read_to_string(&mut buffer)?;
                     // Generated Cargo.toml consists of several sections splitted by '\n\n'
-                    // Here we split and skip the first 5 of them: [package], [package.metadata], [dependencies], [dependencies.<crate_name>] and [workspace]
-                    let parts = buffer.split("\n\n");
-                    let fuzz_target_exists = parts.skip(5).any(|item| {
-                        // In this closure we extract target ident and compare it with the one we want to add.
-                        // If anything goes wrong, this closure returns `false`.
-                        if let Some(target_name_line) = item.lines().nth(1) {
-                            if let Ok(TomlTable(table)) = &target_name_line.parse::<TomlValue>() {
-                                if let Some(TomlString(s)) = table.get("name") {
-                                    s == &ident
-                                } else {
-                                    false
-                                }
-                            } else {
-                                false
-                            }
-                        } else {
-                            false
-                        }
-                    });
+                    // Here we split and skip all of them except for [[bin]] targets, as the
+                    // header differs from one backend to another.
+                    let fuzz_target_exists = buffer
+                        .split("\n\n")
+                        .filter_map(CrateInfo::bin_name)
+                        .any(|name| name == ident);
                     if !fuzz_target_exists {
                         write!(
                             file,
@@ -148,7 +373,7 @@ impl CrateInfo {
                         file.flush()?;
                     }
 
-                    file.unlock()?;
+                    FileExt::unlock(&file)?;
                     Ok(())
                 } else {
                     Err(err)
@@ -157,7 +382,194 @@ impl CrateInfo {
         }
     }
 
-    fn parse_crate_name(cargo_toml_path: &Path) -> Option<String> {
+    /// Removes `[[bin]]` entries, harnesses and dictionaries of targets, which are not in
+    /// `targets`, from the fuzzing crate, so functions, which were renamed or lost their harness,
+    /// do not leave stale targets behind.
+    pub fn retain_fuzz_targets(&self, targets: &[String]) -> Result<(), Error> {
+        let cargo_toml_path = self.fuzz_crate_dir().join("Cargo.toml");
+        let mut file = match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&cargo_toml_path)
+        {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        file.lock_exclusive()?;
+
+        let mut buffer = String::new();
+        file.read_to_string(&mut buffer)?;
+        let mut sections = Vec::new();
+        let mut kept = Vec::new();
+        let mut stale = Vec::new();
+        for section in buffer.split("\n\n") {
+            match CrateInfo::bin_name(section) {
+                Some(name) if targets.contains(&name) => kept.push(name),
+                Some(name) => stale.push(name),
+                None => sections.push(section.trim_end()),
+            }
+        }
+        if !stale.is_empty() {
+            let mut contents = sections.join("\n\n");
+            contents.push('\n');
+            for name in &kept {
+                contents.push_str(&format!(
+                    "{}{}{}{}{}",
+                    &CrateInfo::TARGET_TEMPLATE_PREFIX,
+                    name,
+                    &CrateInfo::TARGET_TEMPLATE_INFIX,
+                    name,
+                    &CrateInfo::TARGET_TEMPLATE_POSTFIX
+                ));
+            }
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(contents.as_bytes())?;
+            file.flush()?;
+
+            self.retain_regressions(&stale)?;
+            let fuzz_dir_path = self.fuzz_dir()?;
+            for name in &stale {
+                for extension in ["rs", "dict"] {
+                    match std::fs::remove_file(
+                        fuzz_dir_path.join(format!("{}.{}", name, extension)),
+                    ) {
+                        Err(err) if err.kind() != ErrorKind::NotFound => return Err(err),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        FileExt::unlock(&file)?;
+        Ok(())
+    }
+
+    /// Removes tests of `stale` targets from regression tests, which `generate_regressions`
+    /// wrote from crash artifacts, as they would call entry points, which no longer exist.
+    fn retain_regressions(&self, stale: &[String]) -> Result<(), Error> {
+        let regressions_path = self.fuzz_crate_dir().join("tests").join("regressions.rs");
+        let regressions = match std::fs::read_to_string(&regressions_path) {
+            Ok(regressions) => regressions,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        // Every test starts with `#[test]` and reads its crash from `artifacts/<target>/`
+        let mut tests = regressions.split("\n#[test]\n");
+        let mut retained = tests.next().unwrap_or_default().to_string();
+        for test in tests {
+            let is_stale = stale
+                .iter()
+                .any(|target| test.contains(&format!("include_bytes!(\"../artifacts/{}/", target)));
+            if !is_stale {
+                retained.push_str("\n#[test]\n");
+                retained.push_str(test);
+            }
+        }
+        if retained != regressions {
+            std::fs::write(&regressions_path, retained)?;
+        }
+        Ok(())
+    }
+
+    /// Name of the target, when a section of the generated Cargo.toml is a `[[bin]]` target.
+    fn bin_name(section: &str) -> Option<String> {
+        if !section.starts_with("[[bin]]") {
+            return None;
+        }
+        match section.lines().nth(1)?.parse::<TomlValue>() {
+            Ok(TomlTable(table)) => match table.get("name") {
+                Some(TomlString(name)) => Some(name.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Features of the crate, which are enabled in the generated Cargo.toml.
+    fn fuzz_crate_features(&self) -> String {
+        let mut features: Vec<String> = self.arbitrary_feature.iter().cloned().collect();
+        for feature in &self.cfg_features {
+            if !features.contains(feature) {
+                features.push(feature.clone());
+            }
+        }
+        if features.is_empty() {
+            return String::new();
+        }
+        let features: Vec<String> = features
+            .iter()
+            .map(|feature| format!("\"{}\"", feature))
+            .collect();
+        format!("features = [{}]\n", features.join(", "))
+    }
+
+    /// Value of a cfg predicate in a fuzzing build with `features` enabled, unless it depends on
+    /// other configuration options.
+    fn evaluate_cfg(predicate: &Meta, features: &[String]) -> Option<bool> {
+        match predicate {
+            Meta::Path(path) if path.is_ident("fuzzing") => Some(true),
+            Meta::Path(path) if path.is_ident("test") => Some(false),
+            Meta::NameValue(MetaNameValue {
+                path,
+                lit: Lit::Str(feature),
+                ..
+            }) if path.is_ident("feature") => Some(features.contains(&feature.value())),
+            Meta::List(list) => {
+                let values: Vec<Option<bool>> = list
+                    .nested
+                    .iter()
+                    .map(|nested| match nested {
+                        NestedMeta::Meta(meta) => CrateInfo::evaluate_cfg(meta, features),
+                        NestedMeta::Lit(_) => None,
+                    })
+                    .collect();
+                // `all` is false and `any` is true, as soon as one of their values is known.
+                let (short_circuit, empty) = if list.path.is_ident("all") {
+                    (false, true)
+                } else if list.path.is_ident("any") {
+                    (true, false)
+                } else if list.path.is_ident("not") && values.len() == 1 {
+                    return values[0].map(|value| !value);
+                } else {
+                    return None;
+                };
+                if values.contains(&Some(short_circuit)) {
+                    Some(short_circuit)
+                } else if values.contains(&None) {
+                    None
+                } else {
+                    Some(empty)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Features, which the predicate checks to be enabled, like `fuzz` in `all(fuzzing, feature = "fuzz")`.
+    fn collect_cfg_features(predicate: &Meta, enabled: bool, features: &mut Vec<String>) {
+        match predicate {
+            Meta::NameValue(MetaNameValue {
+                path,
+                lit: Lit::Str(feature),
+                ..
+            }) if enabled && path.is_ident("feature") && !features.contains(&feature.value()) => {
+                features.push(feature.value())
+            }
+            Meta::List(list) => {
+                let enabled = enabled != list.path.is_ident("not");
+                for nested in &list.nested {
+                    if let NestedMeta::Meta(meta) = nested {
+                        CrateInfo::collect_cfg_features(meta, enabled, features);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn read_cargo_toml(cargo_toml_path: &Path) -> Option<TomlValue> {
         let cargo_bytes = {
             let mut cargo_bytes = Vec::new();
             File::open(cargo_toml_path)
@@ -167,8 +579,10 @@ impl CrateInfo {
             cargo_bytes
         };
 
-        let cargo_toml: TomlValue = toml::from_slice(&cargo_bytes).ok()?;
+        toml::from_slice(&cargo_bytes).ok()
+    }
 
+    fn parse_crate_name(cargo_toml: &TomlValue) -> Option<String> {
         Some(
             cargo_toml
                 .get("package")?
@@ -178,136 +592,713 @@ impl CrateInfo {
         )
     }
 
-    const CARGO_TOML_TEMPLATE_PREFIX: &'static str = r#"[package]
-name = ""#;
+    /// Value of `key` in `[package.metadata.auto-fuzz-test]`.
+    fn metadata<'a>(cargo_toml: &'a TomlValue, key: &str) -> Option<&'a TomlValue> {
+        cargo_toml
+            .get("package")?
+            .get("metadata")?
+            .get("auto-fuzz-test")?
+            .get(key)
+    }
 
-    const CARGO_TOML_TEMPLATE_INFIX: &'static str = r#"-fuzz"
-version = "0.0.0"
-authors = ["Automatically generated"]
-publish = false
-edition = "2018"
+    fn parse_backend(cargo_toml: &TomlValue) -> Result<Backend, String> {
+        match CrateInfo::metadata(cargo_toml, "backend").and_then(TomlValue::as_str) {
+            Some(backend) => backend.parse(),
+            None => Ok(Backend::LibFuzzer),
+        }
+    }
 
-[package.metadata]
-cargo-fuzz = true
+    fn parse_fuzz_cfg(cargo_toml: &TomlValue) -> Result<String, String> {
+        // `cargo fuzz` passes `--cfg fuzzing` to every crate it builds, so it is a sane default.
+        match CrateInfo::metadata(cargo_toml, "cfg") {
+            Some(TomlValue::String(cfg)) => match syn::parse_str::<Meta>(cfg) {
+                Ok(_) => Ok(cfg.clone()),
+                Err(_) => Err(format!("`cfg` is not a configuration predicate: `{}`", cfg)),
+            },
+            Some(value) => Err(format!("`cfg` must be a string, not `{}`", value)),
+            None => Ok("fuzzing".to_string()),
+        }
+    }
 
-[dependencies]
-libfuzzer-sys = "0.4"
+    /// Features, which the fuzzing crate has to enable, so the predicate holds in fuzzing builds.
+    /// Those only set `--cfg fuzzing`, so a predicate, which is false anyway, is rejected.
+    fn parse_cfg_features(cargo_toml: &TomlValue, cfg: &str) -> Result<Vec<String>, String> {
+        let predicate = syn::parse_str::<Meta>(cfg).map_err(|error| error.to_string())?;
+        if CrateInfo::evaluate_cfg(&predicate, &[]) == Some(true) {
+            return Ok(Vec::new());
+        }
+        let mut features = Vec::new();
+        CrateInfo::collect_cfg_features(&predicate, true, &mut features);
+        if CrateInfo::evaluate_cfg(&predicate, &features) == Some(false) {
+            return Err(format!(
+                "`cfg` never holds in fuzzing builds, which only set `fuzzing`: `{}`",
+                cfg
+            ));
+        }
+        match features
+            .iter()
+            .find(|feature| !CrateInfo::has_feature(cargo_toml, feature))
+        {
+            Some(feature) => Err(format!(
+                "`cfg` requires feature `{}`, which is not declared",
+                feature
+            )),
+            None => Ok(features),
+        }
+    }
 
-[dependencies."#;
+    /// Feature could be declared either explicitly, or implicitly by an optional dependency.
+    fn has_feature(cargo_toml: &TomlValue, feature: &str) -> bool {
+        cargo_toml
+            .get("features")
+            .and_then(|features| features.get(feature))
+            .is_some()
+            || cargo_toml
+                .get("dependencies")
+                .and_then(|dependencies| dependencies.get(feature))
+                .and_then(|dependency| dependency.get("optional"))
+                .and_then(|optional| optional.as_bool())
+                .unwrap_or(false)
+    }
+
+    fn parse_property_tests(cargo_toml: &TomlValue) -> Result<bool, String> {
+        CrateInfo::parse_flag(cargo_toml, "property_tests")
+    }
 
-    const CARGO_TOML_TEMPLATE_POSTFIX: &'static str = r#"]
+    /// Switch, which is off by default.
+    fn parse_flag(cargo_toml: &TomlValue, key: &str) -> Result<bool, String> {
+        match CrateInfo::metadata(cargo_toml, key) {
+            Some(TomlValue::Boolean(flag)) => Ok(*flag),
+            Some(value) => Err(format!("`{}` must be a boolean, not `{}`", key, value)),
+            None => Ok(false),
+        }
+    }
+
+    fn parse_seeds(cargo_toml: &TomlValue) -> Result<bool, String> {
+        CrateInfo::parse_flag(cargo_toml, "seeds")
+    }
+
+    fn parse_dictionary(cargo_toml: &TomlValue) -> Result<DictionaryScope, String> {
+        match CrateInfo::metadata(cargo_toml, "dictionary").and_then(TomlValue::as_str) {
+            Some(dictionary) => dictionary.parse(),
+            None => Ok(DictionaryScope::Function),
+        }
+    }
+
+    fn parse_executor(cargo_toml: &TomlValue) -> Result<Option<String>, String> {
+        CrateInfo::parse_path(cargo_toml, "executor")
+    }
+
+    fn parse_setup(cargo_toml: &TomlValue) -> Result<Option<String>, String> {
+        CrateInfo::parse_path(cargo_toml, "setup")
+    }
+
+    /// Path of a function, like `crate::fuzz_setup`, which is checked here, so generated code
+    /// does not fail to parse.
+    fn parse_path(cargo_toml: &TomlValue, key: &str) -> Result<Option<String>, String> {
+        match CrateInfo::metadata(cargo_toml, key) {
+            Some(TomlValue::String(path)) => match syn::parse_str::<syn::Path>(path) {
+                Ok(_) => Ok(Some(path.clone())),
+                Err(_) => Err(format!("`{}` is not a path: `{}`", key, path)),
+            },
+            Some(value) => Err(format!("`{}` must be a string, not `{}`", key, value)),
+            None => Ok(None),
+        }
+    }
+
+    fn arbitrary_dependency(cargo_toml: &TomlValue) -> Option<(&String, &TomlValue)> {
+        // `arbitrary` could be renamed, like `arb = { package = "arbitrary", version = "1" }`
+        cargo_toml
+            .get("dependencies")?
+            .as_table()?
+            .iter()
+            .find(|(name, dependency)| {
+                match dependency
+                    .get("package")
+                    .and_then(|package| package.as_str())
+                {
+                    Some(package) => package == "arbitrary",
+                    None => *name == "arbitrary",
+                }
+            })
+    }
+
+    fn parse_arbitrary_crate(cargo_toml: &TomlValue) -> String {
+        match CrateInfo::arbitrary_dependency(cargo_toml) {
+            Some((name, _)) => name.replace('-', "_"),
+            None => "arbitrary".to_string(),
+        }
+    }
+
+    fn parse_arbitrary_feature(cargo_toml: &TomlValue) -> Option<String> {
+        // Feature could be declared either explicitly, or implicitly by an optional dependency.
+        if cargo_toml
+            .get("features")
+            .and_then(|features| features.get("arbitrary"))
+            .is_some()
+        {
+            return Some("arbitrary".to_string());
+        }
+        CrateInfo::arbitrary_dependency(cargo_toml)
+            .filter(|(_, dependency)| {
+                dependency
+                    .get("optional")
+                    .and_then(|optional| optional.as_bool())
+                    .unwrap_or(false)
+            })
+            .map(|(name, _)| name.to_string())
+    }
+
+    const CARGO_TOML_TEMPLATE_PREFIX: &'static str = r#"[package]
+name = ""#;
+
+    const CARGO_TOML_TEMPLATE_PATH: &'static str = r#"]
 path = ".."
+"#;
 
+    const CARGO_TOML_TEMPLATE_POSTFIX: &'static str = r#"
 # Prevent this from interfering with workspaces
 [workspace]
 members = ["."]
 "#;
 
-    const TARGET_TEMPLATE_PREFIX: &'static str = r#"
-[[bin]]
-name = ""#;
+    const TARGET_TEMPLATE_PREFIX: &'static str = r#"
+[[bin]]
+name = ""#;
+
+    const TARGET_TEMPLATE_INFIX: &'static str = r#""
+path = "fuzz_targets/"#;
+
+    const TARGET_TEMPLATE_POSTFIX: &'static str = r#".rs"
+test = false
+doc = false
+"#;
+}
+
+pub fn construct_harness_ident(
+    function: &Ident,
+    impl_type: Option<&Type>,
+    module_path: &TokenStream,
+) -> syn::Result<String> {
+    // Functions in different modules and/or in different impl's can have identical names, so
+    // every generated item (fuzz struct, fuzz function, target file and `[[bin]]` name) is named
+    // after the full path to the function, with `::` replaced by `__`. Names of distinct paths
+    // still can coincide, like of `foo::bar` and `foo__bar`, `claim_harness_ident` catches these.
+    let mut segments = Vec::new();
+    if !module_path.is_empty() {
+        segments.push(module_path.to_string().replace(" :: ", "__"));
+    }
+    if let Some(typ) = impl_type {
+        segments.push(impl_type_ident(typ)?);
+    }
+    segments.push(function.to_string());
+    Ok(segments.join("__"))
+}
+
+/// Name of the self type of an impl in harness idents. Generic arguments are appended to it,
+/// like `Wrapper_u8` for `Wrapper<u8>`, so impls for different arguments get different names.
+pub fn impl_type_ident(typ: &Type) -> syn::Result<String> {
+    match typ {
+        Type::Path(path) if path.qself.is_none() => {
+            let last = path.path.segments.last().unwrap();
+            let mut ident = last.ident.to_string();
+            if let PathArguments::AngleBracketed(args) = &last.arguments {
+                for arg in &args.args {
+                    if let GenericArgument::Type(_) | GenericArgument::Const(_) = arg {
+                        push_token_idents(quote::quote!(#arg), &mut ident);
+                    }
+                }
+            }
+            Ok(ident)
+        }
+        _ => Err(syn::Error::new_spanned(
+            typ,
+            "Only implementations for simple (like `MyType`) types can be fuzzed",
+        )),
+    }
+}
+
+fn push_token_idents(tokens: TokenStream, ident: &mut String) {
+    for token in tokens {
+        match token {
+            TokenTree::Group(group) => push_token_idents(group.stream(), ident),
+            TokenTree::Ident(name) => {
+                ident.push('_');
+                ident.push_str(&name.to_string());
+            }
+            TokenTree::Literal(literal) => {
+                ident.push('_');
+                ident.extend(
+                    literal
+                        .to_string()
+                        .chars()
+                        .filter(|c| c.is_ascii_alphanumeric() || *c == '_'),
+                );
+            }
+            TokenTree::Punct(_) => {}
+        }
+    }
+}
+
+/// Path of the function, like `foo::<Wrapper<u8>>::new`, for messages about clashing names.
+pub fn function_path(
+    function: &Ident,
+    impl_type: Option<&Type>,
+    module_path: &TokenStream,
+) -> String {
+    let mut segments = Vec::new();
+    if !module_path.is_empty() {
+        segments.push(module_path.to_string().replace(' ', ""));
+    }
+    if let Some(typ) = impl_type {
+        segments.push(format!(
+            "<{}>",
+            quote::quote!(#typ).to_string().replace(' ', "")
+        ));
+    }
+    segments.push(function.to_string());
+    segments.join("::")
+}
+
+/// Harness idents, which were handed out in this compilation, keyed by crate name, with paths of
+/// their functions.
+static HARNESS_IDENTS: Mutex<BTreeMap<(String, String), String>> = Mutex::new(BTreeMap::new());
+
+/// Reserves the harness ident for the function. Fails with the path of another function, whose
+/// harness already has this name, as both would define the same symbols and `[[bin]]` target.
+pub fn claim_harness_ident(
+    crate_name: &str,
+    ident: &str,
+    function_path: &str,
+) -> Result<(), String> {
+    let mut idents = HARNESS_IDENTS
+        .lock()
+        .unwrap_or_else(|error| error.into_inner());
+    let owner = idents
+        .entry((crate_name.to_string(), ident.to_string()))
+        .or_insert_with(|| function_path.to_string());
+    if owner == function_path {
+        Ok(())
+    } else {
+        Err(owner.clone())
+    }
+}
+
+#[cfg(test)]
+impl PartialEq for CrateInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.crate_name == other.crate_name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use quote::{format_ident, quote};
+    use std::fs::File;
+    use std::io::Write;
+    use std::thread;
+    use syn::ItemImpl;
+    use tempfile::tempdir;
+
+    #[test]
+    fn harness_ident_clashes() {
+        let ident = |function: &str, impl_type: Option<&Type>, module_path: &TokenStream| {
+            let function = format_ident!("{}", function);
+            (
+                construct_harness_ident(&function, impl_type, module_path).unwrap(),
+                function_path(&function, impl_type, module_path),
+            )
+        };
+        let claim = |(ident, path): &(String, String)| {
+            claim_harness_ident("harness_ident_clashes", ident, path)
+        };
+
+        // Module segments and underscores in names
+        let nested = ident("bar", None, &quote!(foo));
+        let root = ident("foo__bar", None, &TokenStream::new());
+        assert_eq!(nested.0, root.0);
+        assert_eq!(claim(&nested), Ok(()));
+        assert_eq!(claim(&nested), Ok(()));
+        assert_eq!(claim(&root), Err("foo::bar".to_string()));
+
+        // Modules and impl types
+        let impl_type: Type = syn::parse2(quote!(TestStruct)).unwrap();
+        let module = ident("set_b", None, &quote!(TestStruct));
+        let method = ident("set_b", Some(&impl_type), &TokenStream::new());
+        assert_eq!(module.0, method.0);
+        assert_eq!(claim(&module), Ok(()));
+        assert_eq!(claim(&method), Err("TestStruct::set_b".to_string()));
+
+        // Generic arguments of impl types
+        let small: Type = syn::parse2(quote!(Wrapper<u8>)).unwrap();
+        let large: Type = syn::parse2(quote!(Wrapper<Vec<u16>, 4>)).unwrap();
+        let small = ident("new", Some(&small), &quote!(foo));
+        let large = ident("new", Some(&large), &quote!(foo));
+        assert_eq!(small.0, "foo__Wrapper_u8__new");
+        assert_eq!(large.0, "foo__Wrapper_Vec_u16_4__new");
+        assert_eq!(large.1, "foo::<Wrapper<Vec<u16>,4>>::new");
+        assert_eq!(claim(&small), Ok(()));
+        assert_eq!(claim(&large), Ok(()));
+    }
+
+    #[test]
+    fn harness_ident_complex_self_type() {
+        for impl_type in [
+            quote!(&T),
+            quote!((u8, u8)),
+            quote!([u8; 4]),
+            quote!(dyn Trait),
+        ] {
+            let impl_type: Type = syn::parse2(impl_type).unwrap();
+            assert!(construct_harness_ident(
+                &format_ident!("foo"),
+                Some(&impl_type),
+                &TokenStream::new()
+            )
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn no_cargo_toml() {
+        let dir = tempdir().expect("Could not create a tempdir fot test");
+        assert!(CrateInfo::from_root(dir.path()).is_err());
+    }
+
+    #[test]
+    fn empty_cargo_toml() {
+        let dir = tempdir().expect("Could not create tempdir fot test");
+        let cargo_toml_path = dir.path().join("Cargo.toml");
+        File::create(&cargo_toml_path).expect("Could not create Cargo.toml fot test");
+
+        let cargo_toml = CrateInfo::read_cargo_toml(&cargo_toml_path).unwrap();
+        assert_eq!(CrateInfo::parse_crate_name(&cargo_toml), None);
+    }
+
+    #[test]
+    fn parse_valid_cargo_toml() {
+        let dir = tempdir().expect("Could not create tempdir fot test");
+        let cargo_toml_path = dir.path().join("Cargo.toml");
+        let mut cargo_toml =
+            File::create(&cargo_toml_path).expect("Could not create Cargo.toml fot test");
+        writeln!(cargo_toml, "{}", VALID_CARGO_TOML)
+            .expect("Could not write valid data to Cargo.toml fot test");
+
+        let cargo_toml = CrateInfo::read_cargo_toml(&cargo_toml_path).unwrap();
+        assert_eq!(
+            CrateInfo::parse_crate_name(&cargo_toml),
+            Some("test-lib".to_string())
+        );
+        assert_eq!(
+            CrateInfo::parse_backend(&cargo_toml),
+            Ok(Backend::LibFuzzer)
+        );
+        assert_eq!(
+            CrateInfo::parse_fuzz_cfg(&cargo_toml),
+            Ok("fuzzing".to_string())
+        );
+        assert_eq!(CrateInfo::parse_property_tests(&cargo_toml), Ok(false));
+        assert_eq!(CrateInfo::parse_seeds(&cargo_toml), Ok(false));
+        assert_eq!(
+            CrateInfo::parse_dictionary(&cargo_toml),
+            Ok(DictionaryScope::Function)
+        );
+        assert_eq!(CrateInfo::parse_executor(&cargo_toml), Ok(None));
+        assert_eq!(CrateInfo::parse_setup(&cargo_toml), Ok(None));
+        assert_eq!(CrateInfo::parse_arbitrary_crate(&cargo_toml), "arbitrary");
+        assert_eq!(CrateInfo::parse_arbitrary_feature(&cargo_toml), None);
+    }
+
+    #[test]
+    fn parse_renamed_arbitrary() {
+        let dir = tempdir().expect("Could not create tempdir fot test");
+        let cargo_toml_path = dir.path().join("Cargo.toml");
+        let mut cargo_toml =
+            File::create(&cargo_toml_path).expect("Could not create Cargo.toml fot test");
+        writeln!(cargo_toml, "{}", VALID_CARGO_TOML_RENAMED_ARBITRARY)
+            .expect("Could not write valid data to Cargo.toml fot test");
+
+        let cargo_toml = CrateInfo::read_cargo_toml(&cargo_toml_path).unwrap();
+        assert_eq!(CrateInfo::parse_arbitrary_crate(&cargo_toml), "fuzz_input");
+        assert_eq!(
+            CrateInfo::parse_arbitrary_feature(&cargo_toml),
+            Some("fuzz-input".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_fuzz_config() {
+        let dir = tempdir().expect("Could not create tempdir fot test");
+        let cargo_toml_path = dir.path().join("Cargo.toml");
+        let mut cargo_toml =
+            File::create(&cargo_toml_path).expect("Could not create Cargo.toml fot test");
+        writeln!(cargo_toml, "{}", VALID_CARGO_TOML_OPTIONAL_ARBITRARY)
+            .expect("Could not write valid data to Cargo.toml fot test");
+
+        let cargo_toml = CrateInfo::read_cargo_toml(&cargo_toml_path).unwrap();
+        assert_eq!(
+            CrateInfo::parse_fuzz_cfg(&cargo_toml),
+            Ok("any(fuzzing, feature = \"fuzz\")".to_string())
+        );
+        assert_eq!(CrateInfo::parse_property_tests(&cargo_toml), Ok(true));
+        assert_eq!(CrateInfo::parse_seeds(&cargo_toml), Ok(true));
+        assert_eq!(
+            CrateInfo::parse_dictionary(&cargo_toml),
+            Ok(DictionaryScope::Module)
+        );
+        assert_eq!(
+            CrateInfo::parse_executor(&cargo_toml),
+            Ok(Some("pollster::block_on".to_string()))
+        );
+        assert_eq!(
+            CrateInfo::parse_setup(&cargo_toml),
+            Ok(Some("crate::fuzz_setup".to_string()))
+        );
+        assert_eq!(
+            CrateInfo::parse_arbitrary_feature(&cargo_toml),
+            Some("arbitrary".to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_metadata() {
+        let dir = tempdir().expect("Could not create tempdir fot test");
+        let cargo_toml_path = dir.path().join("Cargo.toml");
+        let mut cargo_toml =
+            File::create(&cargo_toml_path).expect("Could not create Cargo.toml fot test");
+        writeln!(
+            cargo_toml,
+            "{}\n[package.metadata.auto-fuzz-test]\nbackend = \"libfuzz\"",
+            VALID_CARGO_TOML
+        )
+        .expect("Could not write valid data to Cargo.toml fot test");
+
+        let error = CrateInfo::from_root(dir.path()).unwrap_err();
+        assert!(error.contains("Unknown fuzzing backend `libfuzz`"));
+
+        for (metadata, message) in [
+            ("setup = \"crate::\"", "`setup` is not a path: `crate::`"),
+            ("setup = 1", "`setup` must be a string, not `1`"),
+            (
+                "executor = \"tokio::block_on()\"",
+                "`executor` is not a path: `tokio::block_on()`",
+            ),
+            ("executor = true", "`executor` must be a string, not `true`"),
+            (
+                "property_tests = \"yes\"",
+                "`property_tests` must be a boolean, not `\"yes\"`",
+            ),
+            ("seeds = 1", "`seeds` must be a boolean, not `1`"),
+            ("cfg = 1", "`cfg` must be a string, not `1`"),
+            (
+                "cfg = \"any(fuzzing\"",
+                "`cfg` is not a configuration predicate: `any(fuzzing`",
+            ),
+            (
+                "cfg = \"not(fuzzing)\"",
+                "`cfg` never holds in fuzzing builds, which only set `fuzzing`: `not(fuzzing)`",
+            ),
+            (
+                "cfg = 'feature = \"fuzz\"'",
+                "`cfg` requires feature `fuzz`, which is not declared",
+            ),
+        ] {
+            std::fs::write(
+                &cargo_toml_path,
+                format!(
+                    "{}\n[package.metadata.auto-fuzz-test]\n{}\n",
+                    VALID_CARGO_TOML, metadata
+                ),
+            )
+            .unwrap();
+            let error = CrateInfo::from_root(dir.path()).unwrap_err();
+            assert!(error.contains(message), "{}", error);
+        }
+
+        let cargo_toml = CrateInfo::read_cargo_toml(&cargo_toml_path).unwrap();
+        assert_eq!(
+            CrateInfo::parse_dictionary(&cargo_toml),
+            Ok(DictionaryScope::Function)
+        );
+    }
+
+    #[test]
+    fn create_dirs() {
+        let dir = tempdir().expect("Could not create tempdir fot test");
+        let cargo_toml_path = dir.path().join("Cargo.toml");
+        let mut cargo_toml =
+            File::create(&cargo_toml_path).expect("Could not create Cargo.toml fot test");
+        writeln!(cargo_toml, "{}", VALID_CARGO_TOML)
+            .expect("Could not write valid data to Cargo.toml fot test");
+        let crate_info = CrateInfo::from_root(dir.path()).unwrap();
+
+        assert_eq!(
+            crate_info.fuzz_dir().unwrap(),
+            crate_info.crate_root.join("fuzz").join("fuzz_targets")
+        );
+        assert_eq!(
+            crate_info.seed_dir("foo"),
+            crate_info
+                .crate_root
+                .join("fuzz")
+                .join("corpus")
+                .join("foo")
+        );
+        assert_eq!(
+            crate_info.with_backend(Backend::Afl).seed_dir("foo"),
+            crate_info.crate_root.join("afl").join("in").join("foo")
+        );
+    }
+
+    #[test]
+    fn write_cargo_nomodule_noimpl() {
+        let dir = tempdir().expect("Could not create tempdir fot test");
+        let cargo_toml_path = dir.path().join("Cargo.toml");
+        let mut cargo_toml =
+            File::create(&cargo_toml_path).expect("Could not create Cargo.toml fot test");
+        writeln!(cargo_toml, "{}", VALID_CARGO_TOML)
+            .expect("Could not write valid data to Cargo.toml fot test");
+        let crate_info = CrateInfo::from_root(dir.path()).unwrap();
+
+        let ident = format_ident!("foo");
+        let module = TokenStream::new();
+
+        crate_info
+            .add_target_to_cargo_toml(&ident, None, &module)
+            .unwrap();
+
+        crate_info
+            .add_target_to_cargo_toml(&ident, None, &module)
+            .unwrap();
+
+        let mut cargo_toml = File::open(dir.path().join("fuzz").join("Cargo.toml")).unwrap();
+        let mut cargo_contents = String::new();
+        cargo_toml.read_to_string(&mut cargo_contents).unwrap();
+        assert_eq!(cargo_contents, VALID_GENERATED_CARGO_TOML_NOMODULE_NOIMPL);
+    }
 
-    const TARGET_TEMPLATE_INFIX: &'static str = r#""
-path = "fuzz_targets/"#;
+    #[test]
+    fn write_cargo_arbitrary_feature() {
+        let dir = tempdir().expect("Could not create tempdir fot test");
+        let cargo_toml_path = dir.path().join("Cargo.toml");
+        let mut cargo_toml =
+            File::create(&cargo_toml_path).expect("Could not create Cargo.toml fot test");
+        writeln!(cargo_toml, "{}", VALID_CARGO_TOML_OPTIONAL_ARBITRARY)
+            .expect("Could not write valid data to Cargo.toml fot test");
+        let crate_info = CrateInfo::from_root(dir.path()).unwrap();
 
-    const TARGET_TEMPLATE_POSTFIX: &'static str = r#".rs"
-test = false
-doc = false
-"#;
-}
+        let ident = format_ident!("foo");
+        let module = TokenStream::new();
 
-pub fn construct_harness_ident(
-    function: &Ident,
-    impl_type: Option<&Type>,
-    module_path: &TokenStream,
-) -> String {
-    // Functions in different modules and/or in different impl's can have identical names. To
-    // avoid collisions, this function adds module path and impl type to target filenames.
-    match impl_type {
-        Some(typ) => {
-            if let Type::Path(path) = typ {
-                if module_path.is_empty() {
-                    format!(
-                        "{}_{}",
-                        &(path.path.segments.iter().next().unwrap().ident).to_string(),
-                        &function.to_string()
-                    )
-                } else {
-                    format!(
-                        "{}__{}_{}",
-                        module_path.to_string().replace(" :: ", "__"),
-                        &(path.path.segments.iter().next().unwrap().ident).to_string(),
-                        &function.to_string()
-                    )
-                }
-            } else {
-                unimplemented!("Complex self types.")
-            }
-        }
-        None => {
-            if module_path.is_empty() {
-                function.to_string()
-            } else {
-                format!(
-                    "{}__{}",
-                    module_path.to_string().replace(" :: ", "__"),
-                    &function.to_string()
-                )
-            }
-        }
-    }
-}
+        crate_info
+            .add_target_to_cargo_toml(&ident, None, &module)
+            .unwrap();
 
-#[cfg(test)]
-impl PartialEq for CrateInfo {
-    fn eq(&self, other: &Self) -> bool {
-        self.crate_name == other.crate_name
+        let mut cargo_toml = File::open(dir.path().join("fuzz").join("Cargo.toml")).unwrap();
+        let mut cargo_contents = String::new();
+        cargo_toml.read_to_string(&mut cargo_contents).unwrap();
+        assert_eq!(cargo_contents, VALID_GENERATED_CARGO_TOML_ARBITRARY_FEATURE);
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use pretty_assertions::assert_eq;
-    use quote::{format_ident, quote};
-    use std::fs::File;
-    use std::io::Write;
-    use std::thread;
-    use syn::ItemImpl;
-    use tempfile::tempdir;
 
     #[test]
-    fn no_cargo_toml() {
-        let dir = tempdir().expect("Could not create a tempdir fot test");
-        assert_eq!(CrateInfo::from_root(dir.path()), None);
+    fn write_cargo_cfg_features() {
+        let dir = tempdir().expect("Could not create tempdir fot test");
+        let cargo_toml_path = dir.path().join("Cargo.toml");
+        let mut cargo_toml =
+            File::create(&cargo_toml_path).expect("Could not create Cargo.toml fot test");
+        writeln!(
+            cargo_toml,
+            "{}\n[features]\nfuzz = []\n",
+            VALID_CARGO_TOML_OPTIONAL_ARBITRARY.replace(
+                "any(fuzzing, feature = \"fuzz\")",
+                "all(fuzzing, feature = \"fuzz\", not(feature = \"std\"))"
+            )
+        )
+        .expect("Could not write valid data to Cargo.toml fot test");
+        let crate_info = CrateInfo::from_root(dir.path()).unwrap();
+
+        let ident = format_ident!("foo");
+        let module = TokenStream::new();
+
+        crate_info
+            .add_target_to_cargo_toml(&ident, None, &module)
+            .unwrap();
+
+        let mut cargo_toml = File::open(dir.path().join("fuzz").join("Cargo.toml")).unwrap();
+        let mut cargo_contents = String::new();
+        cargo_toml.read_to_string(&mut cargo_contents).unwrap();
+        assert_eq!(
+            cargo_contents,
+            VALID_GENERATED_CARGO_TOML_ARBITRARY_FEATURE.replace(
+                "features = [\"arbitrary\"]",
+                "features = [\"arbitrary\", \"fuzz\"]"
+            )
+        );
     }
 
     #[test]
-    fn empty_cargo_toml() {
+    fn write_cargo_honggfuzz() {
         let dir = tempdir().expect("Could not create tempdir fot test");
         let cargo_toml_path = dir.path().join("Cargo.toml");
-        File::create(&cargo_toml_path).expect("Could not create Cargo.toml fot test");
+        let mut cargo_toml =
+            File::create(&cargo_toml_path).expect("Could not create Cargo.toml fot test");
+        writeln!(cargo_toml, "{}", VALID_CARGO_TOML_HONGGFUZZ)
+            .expect("Could not write valid data to Cargo.toml fot test");
+        let crate_info = CrateInfo::from_root(dir.path()).unwrap();
+        assert_eq!(crate_info.backend(), Backend::Honggfuzz);
+        assert_eq!(
+            crate_info.with_backend(Backend::LibFuzzer).backend(),
+            Backend::LibFuzzer
+        );
+
+        let ident = format_ident!("foo");
+        let module = TokenStream::new();
+
+        crate_info
+            .add_target_to_cargo_toml(&ident, None, &module)
+            .unwrap();
+        crate_info
+            .add_target_to_cargo_toml(&ident, None, &module)
+            .unwrap();
 
-        assert_eq!(CrateInfo::parse_crate_name(&cargo_toml_path), None);
+        let mut cargo_toml = File::open(dir.path().join("hfuzz").join("Cargo.toml")).unwrap();
+        let mut cargo_contents = String::new();
+        cargo_toml.read_to_string(&mut cargo_contents).unwrap();
+        assert_eq!(cargo_contents, VALID_GENERATED_CARGO_TOML_HONGGFUZZ);
     }
 
     #[test]
-    fn parse_valid_cargo_toml() {
+    fn write_cargo_afl() {
         let dir = tempdir().expect("Could not create tempdir fot test");
         let cargo_toml_path = dir.path().join("Cargo.toml");
         let mut cargo_toml =
             File::create(&cargo_toml_path).expect("Could not create Cargo.toml fot test");
         writeln!(cargo_toml, "{}", VALID_CARGO_TOML)
             .expect("Could not write valid data to Cargo.toml fot test");
+        let crate_info = CrateInfo::from_root(dir.path())
+            .unwrap()
+            .with_backend(Backend::Afl);
 
-        assert_eq!(
-            CrateInfo::parse_crate_name(&cargo_toml_path),
-            Some("test-lib".to_string())
-        );
+        let ident = format_ident!("cat");
+        let module = quote!(foo::bar::dog);
+
+        crate_info
+            .add_target_to_cargo_toml(&ident, None, &module)
+            .unwrap();
+        crate_info
+            .add_target_to_cargo_toml(&ident, None, &module)
+            .unwrap();
+
+        let mut cargo_toml = File::open(dir.path().join("afl").join("Cargo.toml")).unwrap();
+        let mut cargo_contents = String::new();
+        cargo_toml.read_to_string(&mut cargo_contents).unwrap();
+        assert_eq!(cargo_contents, VALID_GENERATED_CARGO_TOML_AFL);
     }
 
     #[test]
-    fn create_dirs() {
+    fn read_fuzz_targets() {
         let dir = tempdir().expect("Could not create tempdir fot test");
         let cargo_toml_path = dir.path().join("Cargo.toml");
         let mut cargo_toml =
@@ -316,14 +1307,23 @@ mod tests {
             .expect("Could not write valid data to Cargo.toml fot test");
         let crate_info = CrateInfo::from_root(dir.path()).unwrap();
 
+        let impl_type: Type = syn::parse2(quote!(TestStruct)).unwrap();
+        crate_info
+            .add_target_to_cargo_toml(&format_ident!("cat"), None, &quote!(foo::bar::dog))
+            .unwrap();
+        crate_info
+            .add_target_to_cargo_toml(&format_ident!("foo"), Some(&impl_type), &TokenStream::new())
+            .unwrap();
+
         assert_eq!(
-            crate_info.fuzz_dir().unwrap(),
-            crate_info.crate_root.join("fuzz").join("fuzz_targets")
+            crate_info.fuzz_targets().unwrap(),
+            vec!["foo__bar__dog__cat", "TestStruct__foo"]
         );
+        assert!(crate_info.tests_dir().unwrap().is_dir());
     }
 
     #[test]
-    fn write_cargo_nomodule_noimpl() {
+    fn retain_fuzz_targets() {
         let dir = tempdir().expect("Could not create tempdir fot test");
         let cargo_toml_path = dir.path().join("Cargo.toml");
         let mut cargo_toml =
@@ -332,21 +1332,53 @@ mod tests {
             .expect("Could not write valid data to Cargo.toml fot test");
         let crate_info = CrateInfo::from_root(dir.path()).unwrap();
 
-        let ident = format_ident!("foo");
-        let module = TokenStream::new();
+        // Nothing to prune before the first harness
+        crate_info.retain_fuzz_targets(&[]).unwrap();
+        assert!(!dir.path().join("fuzz").join("Cargo.toml").exists());
 
+        let impl_type: Type = syn::parse2(quote!(TestStruct)).unwrap();
         crate_info
-            .add_target_to_cargo_toml(&ident, None, &module)
+            .add_target_to_cargo_toml(&format_ident!("cat"), None, &quote!(foo::bar::dog))
             .unwrap();
+        crate_info
+            .add_target_to_cargo_toml(&format_ident!("foo"), Some(&impl_type), &TokenStream::new())
+            .unwrap();
+        let fuzz_dir = crate_info.fuzz_dir().unwrap();
+        for file in [
+            "foo__bar__dog__cat.rs",
+            "foo__bar__dog__cat.dict",
+            "TestStruct__foo.rs",
+        ] {
+            std::fs::write(fuzz_dir.join(file), "").unwrap();
+        }
+        std::fs::write(
+            crate_info.tests_dir().unwrap().join("regressions.rs"),
+            REGRESSIONS,
+        )
+        .unwrap();
 
         crate_info
-            .add_target_to_cargo_toml(&ident, None, &module)
+            .retain_fuzz_targets(&["TestStruct__foo".to_string()])
             .unwrap();
+        let cargo_contents =
+            std::fs::read_to_string(dir.path().join("fuzz").join("Cargo.toml")).unwrap();
+        assert_eq!(cargo_contents, VALID_GENERATED_CARGO_TOML_NOMODULE_IMPL);
+        assert!(!fuzz_dir.join("foo__bar__dog__cat.rs").exists());
+        assert!(!fuzz_dir.join("foo__bar__dog__cat.dict").exists());
+        assert!(fuzz_dir.join("TestStruct__foo.rs").exists());
+        assert_eq!(
+            std::fs::read_to_string(crate_info.tests_dir().unwrap().join("regressions.rs"))
+                .unwrap(),
+            REGRESSIONS_RETAINED
+        );
 
-        let mut cargo_toml = File::open(dir.path().join("fuzz").join("Cargo.toml")).unwrap();
-        let mut cargo_contents = String::new();
-        cargo_toml.read_to_string(&mut cargo_contents).unwrap();
-        assert_eq!(cargo_contents, VALID_GENERATED_CARGO_TOML_NOMODULE_NOIMPL);
+        // Targets are still recognized after pruning
+        crate_info
+            .add_target_to_cargo_toml(&format_ident!("foo"), Some(&impl_type), &TokenStream::new())
+            .unwrap();
+        let cargo_contents =
+            std::fs::read_to_string(dir.path().join("fuzz").join("Cargo.toml")).unwrap();
+        assert_eq!(cargo_contents, VALID_GENERATED_CARGO_TOML_NOMODULE_IMPL);
     }
 
     #[test]
@@ -451,18 +1483,18 @@ mod tests {
         // Here comments with numbers are used to enumerate different function idents later they
         // will be used in different threads in different order
         let mut idents_needed = vec![
-            "foo".to_string(),                      // 1
-            "bar".to_string(),                      // 2
-            "foo__foo".to_string(),                 // 3
-            "foo__bar".to_string(),                 // 4
-            "foo__bar__dog__cat".to_string(),       // 5
-            "foo__bar__dog__dog".to_string(),       // 6
-            "TestStruct_foo".to_string(),           // 7
-            "TestStruct_bar".to_string(),           // 8
-            "foo__TestStruct_foo".to_string(),      // 9
-            "foo__TestStruct_bar".to_string(),      // 10
-            "foo__bar__TestStruct_foo".to_string(), // 11
-            "foo__bar__TestStruct_bar".to_string(), // 12
+            "foo".to_string(),                       // 1
+            "bar".to_string(),                       // 2
+            "foo__foo".to_string(),                  // 3
+            "foo__bar".to_string(),                  // 4
+            "foo__bar__dog__cat".to_string(),        // 5
+            "foo__bar__dog__dog".to_string(),        // 6
+            "TestStruct__foo".to_string(),           // 7
+            "TestStruct__bar".to_string(),           // 8
+            "foo__TestStruct__foo".to_string(),      // 9
+            "foo__TestStruct__bar".to_string(),      // 10
+            "foo__bar__TestStruct__foo".to_string(), // 11
+            "foo__bar__TestStruct__bar".to_string(), // 12
         ];
 
         idents_needed.sort();
@@ -709,6 +1741,126 @@ edition = "2018"
 [dependencies]
 auto-fuzz-test = { path = "../"  }
 arbitrary = { version = "1", features = ["derive"]  }
+"#;
+
+    const VALID_CARGO_TOML_OPTIONAL_ARBITRARY: &str = r#"[package]
+name = "test-lib"
+version = "0.1.0"
+authors = ["<test>"]
+edition = "2018"
+
+[package.metadata.auto-fuzz-test]
+cfg = 'any(fuzzing, feature = "fuzz")'
+property_tests = true
+seeds = true
+dictionary = "module"
+executor = "pollster::block_on"
+setup = "crate::fuzz_setup"
+
+[dependencies]
+auto-fuzz-test = { path = "../"  }
+arbitrary = { version = "1", features = ["derive"], optional = true }
+"#;
+
+    const VALID_CARGO_TOML_RENAMED_ARBITRARY: &str = r#"[package]
+name = "test-lib"
+version = "0.1.0"
+authors = ["<test>"]
+edition = "2018"
+
+[dependencies]
+auto-fuzz-test = { path = "../"  }
+fuzz-input = { package = "arbitrary", version = "1", optional = true }
+"#;
+
+    const VALID_GENERATED_CARGO_TOML_ARBITRARY_FEATURE: &str = r#"[package]
+name = "test-lib-fuzz"
+version = "0.0.0"
+authors = ["Automatically generated"]
+publish = false
+edition = "2018"
+
+[package.metadata]
+cargo-fuzz = true
+
+[dependencies]
+libfuzzer-sys = "0.4"
+
+[dependencies.test-lib]
+path = ".."
+features = ["arbitrary"]
+
+# Prevent this from interfering with workspaces
+[workspace]
+members = ["."]
+
+[[bin]]
+name = "foo"
+path = "fuzz_targets/foo.rs"
+test = false
+doc = false
+"#;
+
+    const VALID_CARGO_TOML_HONGGFUZZ: &str = r#"[package]
+name = "test-lib"
+version = "0.1.0"
+authors = ["<test>"]
+edition = "2018"
+
+[package.metadata.auto-fuzz-test]
+backend = "honggfuzz"
+
+[dependencies]
+auto-fuzz-test = { path = "../"  }
+arbitrary = { version = "1", features = ["derive"]  }
+"#;
+
+    const VALID_GENERATED_CARGO_TOML_HONGGFUZZ: &str = r#"[package]
+name = "test-lib-hfuzz"
+version = "0.0.0"
+authors = ["Automatically generated"]
+publish = false
+edition = "2018"
+
+[dependencies]
+honggfuzz = "0.5"
+
+[dependencies.test-lib]
+path = ".."
+
+# Prevent this from interfering with workspaces
+[workspace]
+members = ["."]
+
+[[bin]]
+name = "foo"
+path = "fuzz_targets/foo.rs"
+test = false
+doc = false
+"#;
+
+    const VALID_GENERATED_CARGO_TOML_AFL: &str = r#"[package]
+name = "test-lib-afl"
+version = "0.0.0"
+authors = ["Automatically generated"]
+publish = false
+edition = "2018"
+
+[dependencies]
+afl = "0.15"
+
+[dependencies.test-lib]
+path = ".."
+
+# Prevent this from interfering with workspaces
+[workspace]
+members = ["."]
+
+[[bin]]
+name = "foo__bar__dog__cat"
+path = "fuzz_targets/foo__bar__dog__cat.rs"
+test = false
+doc = false
 "#;
 
     const VALID_GENERATED_CARGO_TOML_NOMODULE_NOIMPL: &str = r#"[package]
@@ -763,6 +1915,35 @@ name = "foo__bar__dog__cat"
 path = "fuzz_targets/foo__bar__dog__cat.rs"
 test = false
 doc = false
+"#;
+
+    const REGRESSIONS: &str = r#"// Autogenerated from crash artifacts by `generate_regressions`.
+#![allow(non_snake_case)]
+
+#[test]
+fn foo__bar__dog__cat__crash_01() {
+    // 1
+    let data = include_bytes!("../artifacts/foo__bar__dog__cat/crash-01");
+    test_lib::__auto_fuzz::__auto_fuzz_test_lib__foo__bar__dog__cat(data);
+}
+
+#[test]
+fn TestStruct__foo__crash_02() {
+    // 2
+    let data = include_bytes!("../artifacts/TestStruct__foo/crash-02");
+    test_lib::__auto_fuzz::__auto_fuzz_test_lib__TestStruct__foo(data);
+}
+"#;
+
+    const REGRESSIONS_RETAINED: &str = r#"// Autogenerated from crash artifacts by `generate_regressions`.
+#![allow(non_snake_case)]
+
+#[test]
+fn TestStruct__foo__crash_02() {
+    // 2
+    let data = include_bytes!("../artifacts/TestStruct__foo/crash-02");
+    test_lib::__auto_fuzz::__auto_fuzz_test_lib__TestStruct__foo(data);
+}
 "#;
 
     const VALID_GENERATED_CARGO_TOML_NOMODULE_IMPL: &str = r#"[package]
@@ -786,8 +1967,8 @@ path = ".."
 members = ["."]
 
 [[bin]]
-name = "TestStruct_foo"
-path = "fuzz_targets/TestStruct_foo.rs"
+name = "TestStruct__foo"
+path = "fuzz_targets/TestStruct__foo.rs"
 test = false
 doc = false
 "#;
@@ -813,8 +1994,8 @@ path = ".."
 members = ["."]
 
 [[bin]]
-name = "foo__bar__dog__TestStruct_cat"
-path = "fuzz_targets/foo__bar__dog__TestStruct_cat.rs"
+name = "foo__bar__dog__TestStruct__cat"
+path = "fuzz_targets/foo__bar__dog__TestStruct__cat.rs"
 test = false
 doc = false
 "#;
@@ -0,0 +1,164 @@
+use crate::scan;
+use proc_macro2::{TokenStream, TokenTree};
+use syn::visit::{self, Visit};
+use syn::{Block, Item, Lit, Macro};
+
+/// libFuzzer ignores longer dictionary entries.
+const MAX_ENTRY_LEN: usize = 64;
+
+/// String, byte string and char literals, which parsers are likely to compare their input with.
+#[derive(Clone, Default)]
+pub struct Literals {
+    entries: Vec<Vec<u8>>,
+}
+
+impl Literals {
+    pub fn add_block(&mut self, block: &Block) {
+        self.visit_block(block);
+    }
+
+    /// Adds literals of module items, skipping submodules and test-only items.
+    pub fn add_items(&mut self, items: &[Item]) {
+        for item in items {
+            let attrs = match item {
+                Item::Mod(_) => continue,
+                Item::Fn(function) => &function.attrs,
+                Item::Impl(implementation) => &implementation.attrs,
+                Item::Const(constant) => &constant.attrs,
+                Item::Static(statik) => &statik.attrs,
+                _ => continue,
+            };
+            if !scan::is_test_only(attrs) && !attrs.iter().any(|attr| attr.path.is_ident("test")) {
+                self.visit_item(item);
+            }
+        }
+    }
+
+    pub fn extend(&mut self, other: &Literals) {
+        for entry in &other.entries {
+            self.push(entry.clone());
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Dictionary in the format shared by libFuzzer, AFL and honggfuzz.
+    pub fn render(&self, target: &str) -> String {
+        let mut dictionary = format!("# Literals of `{}`, autogenerated\n", target);
+        for entry in &self.entries {
+            dictionary.push('"');
+            for &byte in entry {
+                match byte {
+                    b'"' => dictionary.push_str("\\\""),
+                    b'\\' => dictionary.push_str("\\\\"),
+                    b' '..=b'~' => dictionary.push(byte as char),
+                    _ => dictionary.push_str(&format!("\\x{:02x}", byte)),
+                }
+            }
+            dictionary.push_str("\"\n");
+        }
+        dictionary
+    }
+
+    fn push(&mut self, entry: Vec<u8>) {
+        if !entry.is_empty() && entry.len() <= MAX_ENTRY_LEN && !self.entries.contains(&entry) {
+            self.entries.push(entry);
+        }
+    }
+
+    fn add_tokens(&mut self, tokens: TokenStream) {
+        for token in tokens {
+            match token {
+                TokenTree::Literal(literal) => self.visit_lit(&Lit::new(literal)),
+                TokenTree::Group(group) => self.add_tokens(group.stream()),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Literals {
+    fn visit_lit(&mut self, lit: &'ast Lit) {
+        match lit {
+            Lit::Str(lit) => self.push(lit.value().into_bytes()),
+            Lit::ByteStr(lit) => self.push(lit.value()),
+            Lit::Char(lit) => self.push(lit.value().to_string().into_bytes()),
+            Lit::Byte(lit) => self.push(vec![lit.value()]),
+            _ => {}
+        }
+    }
+
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        // Macro arguments are not parsed, so literals are picked from raw tokens
+        self.add_tokens(mac.tokens.clone());
+        visit::visit_macro(self, mac);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use quote::quote;
+    use syn::ItemFn;
+
+    #[test]
+    fn function_literals() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn parse(input: &str) -> Option<u8> {
+                match input {
+                    "GET" | "POST" => Some(1),
+                    _ if input.starts_with('#') => Some(2),
+                    _ if input.as_bytes() == b"\x00\xffMAGIC" => Some(3),
+                    _ => {
+                        assert!(input != "GET", "unreachable \"quote\"");
+                        Some(b'\\' + 4)
+                    }
+                }
+            }
+        })
+        .unwrap();
+        let mut literals = Literals::default();
+        literals.add_block(&function.block);
+        assert_eq!(
+            literals.render("parse"),
+            r##"# Literals of `parse`, autogenerated
+"GET"
+"POST"
+"#"
+"\x00\xffMAGIC"
+"unreachable \"quote\""
+"\\"
+"##
+        );
+    }
+
+    #[test]
+    fn module_literals() {
+        let file: syn::File = syn::parse2(quote! {
+            const KEYWORD: &str = "select";
+            pub fn f(a: u8) -> &'static str {
+                if a == 0 { "zero" } else { KEYWORD }
+            }
+            #[test]
+            fn test_f() {
+                assert_eq!(f(0), "test only");
+            }
+            mod child {
+                const OTHER: &str = "child";
+            }
+        })
+        .unwrap();
+        let mut literals = Literals::default();
+        literals.add_items(&file.items);
+        assert_eq!(literals.entries, [b"select".to_vec(), b"zero".to_vec()]);
+
+        let mut all = Literals::default();
+        all.push(b"zero".to_vec());
+        all.push(vec![b'x'; MAX_ENTRY_LEN + 1]);
+        all.extend(&literals);
+        assert_eq!(all.entries, [b"zero".to_vec(), b"select".to_vec()]);
+    }
+}
@@ -1,16 +1,70 @@
+use crate::attr::{literal_bound, ArgConstraint, Grammar};
+use crate::crate_parse::{construct_harness_ident, Backend};
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 use std::fmt;
 use syn::{
-    Expr, Fields, FnArg, GenericArgument, Ident, ItemFn, ItemStruct, Member, Pat, PathArguments,
-    Signature, Stmt, Type,
+    Attribute, Expr, Fields, FnArg, GenericArgument, Ident, ItemFn, ItemImpl, ItemStruct, Member,
+    ParenthesizedGenericArguments, Pat, Path, PathArguments, RangeLimits, ReturnType, Signature,
+    Stmt, Type, TypeParamBound,
 };
 
-pub fn fuzz_struct(signature: &Signature, impl_type: Option<&Type>) -> Result<ItemStruct, Error> {
+/// Standard library types, which `arbitrary` lacks impls for in some or all of its 1.x versions.
+/// Arguments of these types are decoded through `auto_fuzz_test_runtime::adapter::Adapter`.
+const ADAPTED_TYPES: &[&str] = &[
+    "IpAddr",
+    "Ipv4Addr",
+    "Ipv6Addr",
+    "SocketAddr",
+    "SocketAddrV4",
+    "SocketAddrV6",
+    "NonZeroU8",
+    "NonZeroU16",
+    "NonZeroU32",
+    "NonZeroU64",
+    "NonZeroU128",
+    "NonZeroUsize",
+    "NonZeroI8",
+    "NonZeroI16",
+    "NonZeroI32",
+    "NonZeroI64",
+    "NonZeroI128",
+    "NonZeroIsize",
+    "SystemTime",
+    "Ordering",
+];
+
+/// Whether the function takes nothing but a `&[u8]`. Such functions get the fuzzer's input as is,
+/// without a fuzz struct in between, so libFuzzer minimises inputs and applies dictionary entries
+/// to the bytes the function actually sees.
+pub fn is_raw(signature: &Signature) -> bool {
+    if signature.inputs.len() != 1 {
+        return false;
+    }
+    match signature.inputs.first() {
+        Some(FnArg::Typed(arg)) => match (&*arg.pat, &*arg.ty) {
+            (Pat::Ident(_), Type::Reference(reference)) => {
+                reference.mutability.is_none()
+                    && matches!(&*reference.elem, Type::Slice(slice) if matches!(
+                        &*slice.elem,
+                        Type::Path(path) if path.qself.is_none() && path.path.is_ident("u8")
+                    ))
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+pub fn fuzz_struct(
+    signature: &Signature,
+    impl_type: Option<&Type>,
+    module_path: &TokenStream,
+) -> Result<ItemStruct, Error> {
     // struct for function arguments template
     let mut fuzz_struct: ItemStruct = syn::parse2(quote! {
-        #[derive(Arbitrary)]
         #[derive(Debug)]
+        #[allow(non_camel_case_types)]
         pub struct fuzz {
             a:u32,
             b:Box<u64>
@@ -19,22 +73,10 @@ pub fn fuzz_struct(signature: &Signature, impl_type: Option<&Type>) -> Result<It
     .unwrap();
 
     // Struct ident generation
-    fuzz_struct.ident = match impl_type {
-        Some(typ) => {
-            if let Type::Path(path) = typ {
-                format_ident!(
-                    "__fuzz_struct_{}_{}",
-                    &(path.path.segments.iter().next().unwrap().ident).to_string(),
-                    &(*signature).ident.to_string()
-                )
-            } else {
-                return Err(Error::ComplexSelfType);
-            }
-        }
-        None => {
-            format_ident!("__fuzz_struct_{}", &(*signature).ident.to_string())
-        }
-    };
+    fuzz_struct.ident = format_ident!(
+        "__fuzz_struct_{}",
+        fuzz_ident(signature, impl_type, module_path)?
+    );
 
     // Struct fields generation
     if let Fields::Named(ref mut fields) = fuzz_struct.fields {
@@ -57,11 +99,30 @@ pub fn fuzz_struct(signature: &Signature, impl_type: Option<&Type>) -> Result<It
                 unBoxed variable",
             )
             .into_value();
-        for item in (*signature).inputs.iter() {
+        for item in signature.inputs.iter() {
             match item {
                 FnArg::Typed(i) => {
                     if let Pat::Ident(id) = &*i.pat {
                         match *i.ty.clone() {
+                            ref closure_type if closure_signature(closure_type).is_some() => {
+                                // `variable` is a new struct field
+                                let mut variable = default_variable.clone();
+                                variable.ident = Some(id.ident.clone());
+                                // Return values of the closure
+                                let output = match &closure_signature(closure_type).unwrap().output
+                                {
+                                    ReturnType::Default => quote!(()),
+                                    ReturnType::Type(_, output) => match **output {
+                                        Type::Reference(_) => return Err(Error::ComplexArg),
+                                        ref output => quote!(#output),
+                                    },
+                                };
+                                variable.ty = syn::parse_quote!(
+                                    ::auto_fuzz_test_runtime::closure::ReturnTable<#output>
+                                );
+                                // Pushing variable type for the struct field
+                                fields.named.push(variable);
+                            }
                             Type::Reference(rf) => {
                                 if let Type::Path(path) = *rf.elem.clone() {
                                     // `variable` is a new struct field
@@ -169,23 +230,281 @@ pub fn fuzz_struct(signature: &Signature, impl_type: Option<&Type>) -> Result<It
     Ok(fuzz_struct)
 }
 
-pub fn fuzz_function(signature: &Signature, impl_type: Option<&Type>) -> Result<ItemFn, Error> {
-    // Checking that the function meets our requirements
-    if signature.asyncness.is_some() {
-        return Err(Error::Async);
+pub fn fuzz_arbitrary_impl(
+    fuzz_struct: &ItemStruct,
+    signature: &Signature,
+    arbitrary_crate: &Ident,
+) -> Result<ItemImpl, Error> {
+    // `#[derive(Arbitrary)]` expands to paths like `arbitrary::Unstructured`, which only resolve
+    // when `arbitrary` is in scope under its own name. Implementing the trait by hand through
+    // absolute paths works from any module, and with a renamed dependency as well.
+    let ident = &fuzz_struct.ident;
+    let mut fields = Vec::new();
+    for field in fuzz_struct.fields.iter() {
+        let field_ident = &field.ident;
+        let arg = signature.inputs.iter().find_map(|input| match input {
+            FnArg::Typed(arg) => match &*arg.pat {
+                Pat::Ident(pat) if Some(&pat.ident) == field_ident.as_ref() => Some(arg),
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        });
+        let constraint = match arg {
+            Some(arg) => ArgConstraint::from_attrs(&arg.attrs)
+                .map_err(|error| Error::Constraint(error.to_string()))?,
+            None => None,
+        };
+        let value = match (arg, constraint) {
+            (Some(arg), Some(constraint)) => {
+                constrained_value(&arg.ty, &constraint, arbitrary_crate)
+            }
+            (Some(arg), None) => match adapted_type(&arg.ty) {
+                Some(value_type) => quote!({
+                    let value: ::auto_fuzz_test_runtime::adapter::Adapter<#value_type> =
+                        ::#arbitrary_crate::Arbitrary::arbitrary(u)?;
+                    ::std::convert::Into::into(value.0)
+                }),
+                None => match closure_signature(&arg.ty).map(|signature| &signature.output) {
+                    // Return values of closures are decoded through the adapter one by one
+                    Some(ReturnType::Type(_, output)) if adapted_type(output).is_some() => {
+                        quote!(::auto_fuzz_test_runtime::closure::ReturnTable::arbitrary_with(
+                            u,
+                            |u| {
+                                let value: ::auto_fuzz_test_runtime::adapter::Adapter<#output> =
+                                    ::#arbitrary_crate::Arbitrary::arbitrary(u)?;
+                                Ok(value.0)
+                            },
+                        )?)
+                    }
+                    _ => quote!(::#arbitrary_crate::Arbitrary::arbitrary(u)?),
+                },
+            },
+            _ => quote!(::#arbitrary_crate::Arbitrary::arbitrary(u)?),
+        };
+        fields.push(quote!(#field_ident: #value));
     }
-    if signature.unsafety.is_some() {
+    Ok(syn::parse2(quote! {
+        impl<'a> ::#arbitrary_crate::Arbitrary<'a> for #ident {
+            fn arbitrary(
+                u: &mut ::#arbitrary_crate::Unstructured<'a>,
+            ) -> ::#arbitrary_crate::Result<Self> {
+                Ok(#ident {
+                    #(#fields),*
+                })
+            }
+        }
+    })
+    .unwrap())
+}
+
+/// Signature of a closure argument, like `impl Fn(&T) -> bool` or `&mut dyn FnMut(u8)`.
+fn closure_signature(arg_type: &Type) -> Option<&ParenthesizedGenericArguments> {
+    let bounds = match arg_type {
+        Type::ImplTrait(impl_trait) => &impl_trait.bounds,
+        Type::Reference(reference) => match &*reference.elem {
+            Type::TraitObject(trait_object) => &trait_object.bounds,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    bounds.iter().find_map(|bound| match bound {
+        TypeParamBound::Trait(bound) => {
+            let last = bound.path.segments.last()?;
+            match &last.arguments {
+                PathArguments::Parenthesized(signature)
+                    if ["Fn", "FnMut", "FnOnce"]
+                        .iter()
+                        .any(|name| last.ident == name) =>
+                {
+                    Some(signature)
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    })
+}
+
+/// Closure, which answers with values from the `ReturnTable` in the field, picked by its arguments.
+/// The table is moved out of the fuzz struct, so the closure may be `'static`.
+fn closure_arg(field: &Ident, closure_type: &Type) -> Expr {
+    let params: Vec<Ident> = (0..closure_signature(closure_type)
+        .expect("Closure argument must have a signature")
+        .inputs
+        .len())
+        .map(|index| format_ident!("arg{}", index))
+        .collect();
+    let closure = quote!({
+        let table = input.#field;
+        move |#(#params),*| {
+            use ::auto_fuzz_test_runtime::closure::{Args, HashArgs as _, OpaqueArgs as _};
+            table.answer((&Args(&(#(&#params,)*))).key())
+        }
+    });
+    match closure_type {
+        Type::Reference(reference) if reference.mutability.is_some() => {
+            syn::parse_quote!(&mut #closure)
+        }
+        Type::Reference(_) => syn::parse_quote!(&#closure),
+        _ => syn::parse_quote!(#closure),
+    }
+}
+
+/// Type of the argument, if it is one of `ADAPTED_TYPES`. Only bare names and paths from `std`
+/// or `core` are matched, so user types with the same name are left alone, unless imported.
+fn adapted_type(arg_type: &Type) -> Option<&Type> {
+    let value_type = match arg_type {
+        Type::Reference(reference) => &*reference.elem,
+        arg_type => arg_type,
+    };
+    if let Type::Path(path) = value_type {
+        let segments = &path.path.segments;
+        let last = segments.last()?;
+        let from_std = segments.len() == 1
+            || ["std", "core"]
+                .iter()
+                .any(|krate| segments[0].ident == krate);
+        if path.qself.is_none()
+            && from_std
+            && last.arguments.is_empty()
+            && ADAPTED_TYPES.iter().any(|name| last.ident == name)
+        {
+            return Some(value_type);
+        }
+    }
+    None
+}
+
+/// Expression, which only produces values in the domain of the argument, or calls its custom
+/// generator. Borrowed arguments are boxed in the fuzz struct, so the value is converted into
+/// the field type.
+fn constrained_value(
+    arg_type: &Type,
+    constraint: &ArgConstraint,
+    arbitrary_crate: &Ident,
+) -> TokenStream {
+    let value_type = match arg_type {
+        Type::Reference(reference) => &*reference.elem,
+        arg_type => arg_type,
+    };
+    let is_string = match value_type {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "str" || segment.ident == "String"),
+        _ => false,
+    };
+    let value_type = if is_string {
+        quote!(::std::string::String)
+    } else {
+        quote!(#value_type)
+    };
+
+    let value = if let Some(with) = &constraint.with {
+        quote!(let value = #with(u)?;)
+    } else if let Some(grammar) = &constraint.grammar {
+        let grammar = match grammar {
+            Grammar::Builtin(name) => {
+                let name = format_ident!("{}", name.to_uppercase());
+                quote!(::auto_fuzz_test_runtime::grammar::#name)
+            }
+            Grammar::File(path) => quote!({
+                static GRAMMAR: ::auto_fuzz_test_runtime::grammar::LazyGrammar =
+                    ::auto_fuzz_test_runtime::grammar::LazyGrammar::new(include_str!(concat!(
+                        env!("CARGO_MANIFEST_DIR"),
+                        "/",
+                        #path
+                    )));
+                &GRAMMAR
+            }),
+        };
+        quote!(let value: ::std::string::String = #grammar.generate(u)?;)
+    } else if let Some(range) = &constraint.range {
+        let start = match &range.from {
+            Some(start) => quote!(#start),
+            None => quote!(<#value_type>::MIN),
+        };
+        // Empty ranges are not an error of the fuzzer's input, but there is no value to decode
+        let empty = quote!(return Err(::#arbitrary_crate::Error::IncorrectFormat));
+        let end = match (&range.to, &range.limits) {
+            (Some(end), RangeLimits::Closed(_)) => quote!(#end),
+            (Some(end), RangeLimits::HalfOpen(_)) => quote! {
+                match <#value_type>::checked_sub(#end, 1) {
+                    Some(end) => end,
+                    None => #empty,
+                }
+            },
+            (None, _) => quote!(<#value_type>::MAX),
+        };
+        // Literal bounds are checked, when the attribute is parsed
+        let guard = match (&range.from, &range.to) {
+            (Some(from), Some(to))
+                if literal_bound(from).is_none() || literal_bound(to).is_none() =>
+            {
+                quote! {
+                    if start > end {
+                        #empty;
+                    }
+                }
+            }
+            _ => TokenStream::new(),
+        };
+        quote! {
+            let start: #value_type = #start;
+            let end: #value_type = #end;
+            #guard
+            let value: #value_type = u.int_in_range(start..=end)?;
+        }
+    } else if let Some(max_len) = &constraint.max_len {
+        let truncate = if is_string {
+            quote! {
+                let mut len = value.len().min(#max_len);
+                while !value.is_char_boundary(len) {
+                    len -= 1;
+                }
+                value.truncate(len);
+            }
+        } else {
+            quote!(value.truncate(#max_len);)
+        };
+        quote! {
+            let mut value: #value_type = ::#arbitrary_crate::Arbitrary::arbitrary(u)?;
+            #truncate
+        }
+    } else {
+        unreachable!("Constraint must have one of range, max_len, with or grammar");
+    };
+
+    quote!({
+        #value
+        ::std::convert::Into::into(value)
+    })
+}
+
+pub fn fuzz_function(
+    signature: &Signature,
+    impl_type: Option<&Type>,
+    module_path: &TokenStream,
+    executor: Option<&Path>,
+    precondition: Option<&Path>,
+) -> Result<ItemFn, Error> {
+    // Checking that the function meets our requirements
+    if signature.unsafety.is_some() && precondition.is_none() {
         return Err(Error::Unsafe);
     }
-    if signature.inputs.is_empty() {
-        return Err(Error::Empty);
+
+    if is_raw(signature) {
+        let mut fuzz_function = raw_fuzz_function(signature, impl_type, module_path)?;
+        wrap_call(&mut fuzz_function, signature, executor, precondition)?;
+        return Ok(fuzz_function);
     }
 
     let mut fuzz_function: syn::ItemFn;
 
     if let Some(typ) = impl_type {
-        match (*signature).inputs.first().unwrap() {
-            FnArg::Receiver(_) => {
+        match signature.inputs.first() {
+            Some(FnArg::Receiver(_)) => {
                 // method harness template
                 fuzz_function = syn::parse2(quote! {
                     pub fn fuzz(mut input:MyStruct) {
@@ -198,18 +517,23 @@ pub fn fuzz_function(signature: &Signature, impl_type: Option<&Type>) -> Result<
                     &mut fuzz_function.block.stmts[0]
                 {
                     // MethodCall inside fuzzing function
-                    method_call.method = (*signature).ident.clone();
+                    method_call.method = signature.ident.clone();
 
                     // Arguments for internal method call
                     let args = &mut method_call.args;
                     let default_borrowed_field = args.pop().unwrap().into_value();
                     let default_field = args.pop().unwrap().into_value();
 
-                    for item in (*signature).inputs.iter().skip(1) {
+                    for item in signature.inputs.iter().skip(1) {
                         match item {
                             FnArg::Typed(i) => {
                                 if let Pat::Ident(id) = &*i.pat {
                                     match *i.ty.clone() {
+                                        ref closure_type
+                                            if closure_signature(closure_type).is_some() =>
+                                        {
+                                            args.push(closure_arg(&id.ident, closure_type));
+                                        }
                                         Type::Reference(rf) => {
                                             let mut new_field = default_borrowed_field.clone();
                                             if let Expr::Reference(ref mut new_rf) = new_field {
@@ -266,7 +590,7 @@ pub fn fuzz_function(signature: &Signature, impl_type: Option<&Type>) -> Result<
                     unreachable!("Wrong method call template.")
                 }
             }
-            FnArg::Typed(_) => {
+            _ => {
                 // method harness template
                 fuzz_function = syn::parse2(quote! {
                     pub fn fuzz(mut input:MyStruct) {
@@ -277,15 +601,12 @@ pub fn fuzz_function(signature: &Signature, impl_type: Option<&Type>) -> Result<
 
                 if let Stmt::Semi(Expr::Call(fn_call), _) = &mut fuzz_function.block.stmts[0] {
                     // FnCall inside fuzzing function
-                    if let Expr::Path(path) = &mut *fn_call.func {
-                        let mut segments_iter = path.path.segments.iter_mut();
-                        if let Type::Path(type_path) = typ {
-                            segments_iter.next().unwrap().ident =
-                                type_path.path.segments.first().unwrap().ident.clone();
-                        } else {
-                            return Err(Error::ComplexMethodCall);
-                        }
-                        segments_iter.next().unwrap().ident = (*signature).ident.clone();
+                    // Qualified path keeps every segment and generic argument of the self type
+                    if let Type::Path(_) = typ {
+                        let ident = &signature.ident;
+                        *fn_call.func = syn::parse2(quote!(<#typ>::#ident)).unwrap();
+                    } else {
+                        return Err(Error::ComplexMethodCall);
                     }
 
                     // Arguments for internal function call
@@ -293,11 +614,16 @@ pub fn fuzz_function(signature: &Signature, impl_type: Option<&Type>) -> Result<
                     let default_borrowed_field = args.pop().unwrap().into_value();
                     let default_field = args.pop().unwrap().into_value();
 
-                    for item in (*signature).inputs.iter() {
+                    for item in signature.inputs.iter() {
                         match item {
                             FnArg::Typed(i) => {
                                 if let Pat::Ident(id) = &*i.pat {
                                     match *i.ty.clone() {
+                                        ref closure_type
+                                            if closure_signature(closure_type).is_some() =>
+                                        {
+                                            args.push(closure_arg(&id.ident, closure_type));
+                                        }
                                         Type::Reference(rf) => {
                                             let mut new_field = default_borrowed_field.clone();
                                             if let Expr::Reference(ref mut new_rf) = new_field {
@@ -369,7 +695,7 @@ pub fn fuzz_function(signature: &Signature, impl_type: Option<&Type>) -> Result<
         if let Stmt::Semi(Expr::Call(fn_call), _) = &mut fuzz_function.block.stmts[0] {
             // FnCall inside fuzzing function
             if let Expr::Path(path) = &mut *fn_call.func {
-                path.path.segments.iter_mut().next().unwrap().ident = (*signature).ident.clone();
+                path.path.segments.iter_mut().next().unwrap().ident = signature.ident.clone();
             } else {
                 unreachable!("Wrong function harness template.")
             }
@@ -379,11 +705,14 @@ pub fn fuzz_function(signature: &Signature, impl_type: Option<&Type>) -> Result<
             let default_borrowed_field = args.pop().unwrap().into_value();
             let default_field = args.pop().unwrap().into_value();
 
-            for item in (*signature).inputs.iter() {
+            for item in signature.inputs.iter() {
                 match item {
                     FnArg::Typed(i) => {
                         if let Pat::Ident(id) = &*i.pat {
                             match *i.ty.clone() {
+                                ref closure_type if closure_signature(closure_type).is_some() => {
+                                    args.push(closure_arg(&id.ident, closure_type));
+                                }
                                 Type::Reference(rf) => {
                                     let mut new_field = default_borrowed_field.clone();
                                     if let Expr::Reference(ref mut new_rf) = new_field {
@@ -439,232 +768,1467 @@ pub fn fuzz_function(signature: &Signature, impl_type: Option<&Type>) -> Result<
         }
     }
 
+    let ident = fuzz_ident(signature, impl_type, module_path)?;
+
     // Fuzing function input type
     if let FnArg::Typed(i) = fuzz_function.sig.inputs.iter_mut().next().unwrap() {
         if let Type::Path(typ) = &mut *i.ty {
-            typ.path.segments.iter_mut().next().unwrap().ident = match impl_type {
-                Some(typ) => {
-                    if let Type::Path(path) = typ {
-                        format_ident!(
-                            "__fuzz_struct_{}_{}",
-                            &(path.path.segments.iter().next().unwrap().ident).to_string(),
-                            &(*signature).ident.to_string()
-                        )
-                    } else {
-                        return Err(Error::ComplexSelfType);
-                    }
-                }
-                None => {
-                    format_ident!("__fuzz_struct_{}", &(*signature).ident.to_string())
-                }
-            };
+            typ.path.segments.iter_mut().next().unwrap().ident =
+                format_ident!("__fuzz_struct_{}", ident);
+        }
+        // Functions without arguments leave the empty fuzz struct unused
+        if signature.inputs.is_empty() {
+            *i.pat = syn::parse_quote!(_input);
         }
     }
 
     // Fuzzing function ident
-    fuzz_function.sig.ident = match impl_type {
-        Some(typ) => {
-            if let Type::Path(path) = typ {
-                format_ident!(
-                    "__fuzz_{}_{}",
-                    &(path.path.segments.iter().next().unwrap().ident).to_string(),
-                    &(*signature).ident.to_string()
-                )
-            } else {
-                return Err(Error::ComplexSelfType);
-            }
-        }
-        None => {
-            format_ident!("__fuzz_{}", &(*signature).ident.to_string())
-        }
-    };
+    fuzz_function.sig.ident = format_ident!("__fuzz_{}", ident);
+    fuzz_function
+        .attrs
+        .push(syn::parse_quote!(#[allow(non_snake_case)]));
+
+    wrap_call(&mut fuzz_function, signature, executor, precondition)?;
 
     Ok(fuzz_function)
 }
 
-pub fn fuzz_harness(
+/// Adapts the call in the fuzzing function to unsafe and async callees.
+fn wrap_call(
+    fuzz_function: &mut ItemFn,
     signature: &Signature,
-    impl_type: Option<&Type>,
-    crate_ident: &Ident,
-    attr: &TokenStream,
-) -> TokenStream {
-    // Idents generation
-    let arg_type = match impl_type {
-        Some(typ) => {
-            if let Type::Path(path) = typ {
-                format_ident!(
-                    "__fuzz_struct_{}_{}",
-                    &(path.path.segments.iter().next().unwrap().ident).to_string(),
-                    &(*signature).ident.to_string()
-                )
-            } else {
-                unimplemented!("Complex self type.")
-            }
-        }
-        None => {
-            format_ident!("__fuzz_struct_{}", &(*signature).ident.to_string())
+    executor: Option<&Path>,
+    precondition: Option<&Path>,
+) -> Result<(), Error> {
+    if signature.unsafety.is_some() {
+        let call = match &fuzz_function.block.stmts[0] {
+            Stmt::Semi(call, _) => call.clone(),
+            _ => unreachable!("Wrong fuzzing function template."),
+        };
+        fuzz_function.block.stmts[0] = syn::parse_quote!(unsafe { #call };);
+    }
+    if signature.asyncness.is_some() {
+        block_on(fuzz_function, executor);
+    }
+    if let Some(precondition) = precondition {
+        guard(fuzz_function, signature, precondition)?;
+    }
+    Ok(())
+}
+
+/// Skips inputs, which the user's predicate rejects. The predicate takes the arguments of the
+/// call by shared reference, e.g. `fn(&[u8], &usize) -> bool` for `unsafe fn(&[u8], usize)`.
+fn guard(
+    fuzz_function: &mut ItemFn,
+    signature: &Signature,
+    precondition: &Path,
+) -> Result<(), Error> {
+    let args = if is_raw(signature) {
+        vec![quote!(data)]
+    } else {
+        signature
+            .inputs
+            .iter()
+            .map(|input| match input {
+                FnArg::Receiver(_) => Ok(quote!(&input.slf)),
+                FnArg::Typed(typed) => match (&*typed.pat, &*typed.ty) {
+                    (Pat::Ident(pat), Type::Reference(_)) => {
+                        let field = &pat.ident;
+                        Ok(quote!(&*input.#field))
+                    }
+                    (Pat::Ident(pat), _) => {
+                        let field = &pat.ident;
+                        Ok(quote!(&input.#field))
+                    }
+                    _ => Err(Error::ComplexVariable),
+                },
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    let call = &fuzz_function.block.stmts[0];
+    fuzz_function.block.stmts[0] = syn::parse_quote! {
+        if #precondition(#(#args),*) {
+            #call
         }
     };
+    Ok(())
+}
 
-    let function_ident = match impl_type {
-        Some(typ) => {
-            if let Type::Path(path) = typ {
-                format_ident!(
-                    "__fuzz_{}_{}",
-                    &(path.path.segments.iter().next().unwrap().ident).to_string(),
-                    &(*signature).ident.to_string()
-                )
-            } else {
-                unimplemented!("Complex self type.")
+/// Drives the future, which the call in the fuzzing function returns, to completion. Without a
+/// user's executor, the future is polled on the current thread, which is parked until the future
+/// is woken, so no runtime dependency is needed. Futures, which wait for I/O or timers of some
+/// runtime, never complete this way, so these need an executor.
+fn block_on(fuzz_function: &mut ItemFn, executor: Option<&Path>) {
+    let call = match &fuzz_function.block.stmts[0] {
+        Stmt::Semi(call, _) => call.clone(),
+        _ => unreachable!("Wrong fuzzing function template."),
+    };
+    fuzz_function.block.stmts[0] = match executor {
+        Some(executor) => syn::parse_quote!(#executor(#call);),
+        None => syn::parse_quote!({
+            struct ThreadWaker(::std::thread::Thread);
+            impl ::std::task::Wake for ThreadWaker {
+                fn wake(self: ::std::sync::Arc<Self>) {
+                    self.0.unpark();
+                }
             }
-        }
-        None => {
-            format_ident!("__fuzz_{}", &(*signature).ident.to_string())
-        }
+            let waker = ::std::task::Waker::from(::std::sync::Arc::new(ThreadWaker(
+                ::std::thread::current(),
+            )));
+            let mut context = ::std::task::Context::from_waker(&waker);
+            let mut future = ::std::boxed::Box::pin(#call);
+            while ::std::future::Future::poll(future.as_mut(), &mut context).is_pending() {
+                ::std::thread::park();
+            }
+        }),
     };
+}
 
-    let path = {
-        if attr.is_empty() {
-            quote!(#crate_ident ::)
-        } else {
-            quote!(#crate_ident :: #attr ::)
+fn raw_fuzz_function(
+    signature: &Signature,
+    impl_type: Option<&Type>,
+    module_path: &TokenStream,
+) -> Result<ItemFn, Error> {
+    if let Some(FnArg::Typed(arg)) = signature.inputs.first() {
+        if ArgConstraint::from_attrs(&arg.attrs)
+            .map_err(|error| Error::Constraint(error.to_string()))?
+            .is_some()
+        {
+            return Err(Error::Constraint(
+                "raw byte slice arguments take the fuzzer's input as is".to_string(),
+            ));
         }
+    }
+    let ident = &signature.ident;
+    let callee = match impl_type {
+        Some(typ @ Type::Path(_)) => quote!(<#typ>::#ident),
+        Some(_) => return Err(Error::ComplexMethodCall),
+        None => quote!(#ident),
     };
+    let function_ident = format_ident!("__fuzz_{}", fuzz_ident(signature, impl_type, module_path)?);
 
-    let code = quote!(
-        // Autogenerated fuzzing harness.
-        #![no_main]
-        use libfuzzer_sys::fuzz_target;
-        extern crate #crate_ident;
+    Ok(syn::parse2(quote! {
+        #[allow(non_snake_case)]
+        pub fn #function_ident(data: &[u8]) {
+            #callee(data);
+        }
+    })
+    .unwrap())
+}
 
-        fuzz_target!(|input: #path #arg_type| {
-        #path #function_ident (input);
-        });
-    );
+pub fn fuzz_entry(
+    signature: &Signature,
+    impl_type: Option<&Type>,
+    module_path: &TokenStream,
+    crate_ident: &Ident,
+    arbitrary_crate: &Ident,
+    setup: Option<&Path>,
+) -> Result<ItemFn, Error> {
+    // Harness lives in a separate crate, so it can not name items in private modules. Instead,
+    // every target gets an entry point, which `entry_hub` re-exports from the crate root.
+    let ident = fuzz_ident(signature, impl_type, module_path)?;
+    let entry_ident = entry_ident(crate_ident, &ident);
+    let arg_type = format_ident!("__fuzz_struct_{}", ident);
+    let function_ident = format_ident!("__fuzz_{}", ident);
+    let (prepare, input) = prepared_input(quote!(data), arbitrary_crate, setup, quote!(return));
 
-    code
-}
+    if is_raw(signature) {
+        let data = raw_input(&input, setup);
+        return Ok(syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn #entry_ident(data: &[u8]) {
+                #prepare
+                #function_ident(#data);
+            }
+        })
+        .unwrap());
+    }
 
-#[derive(Debug, PartialEq)]
-pub enum Error {
-    Unsafe,
-    Async,
-    Empty,
-    ComplexArg,
-    ComplexSelfType,
-    MultipleRes,
-    ComplexMethodCall,
-    ComplexVariable,
+    Ok(syn::parse2(quote! {
+        #[allow(non_snake_case)]
+        pub fn #entry_ident(data: &[u8]) {
+            #prepare
+            if let Ok(input) = <#arg_type as ::#arbitrary_crate::Arbitrary>::arbitrary_take_rest(
+                #input,
+            ) {
+                #function_ident(input);
+            }
+        }
+    })
+    .unwrap())
 }
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let err_msg = match self {
-            Error::Async => "Can not fuzz async functions.",
-            Error::Unsafe => "unsafe functions can not be fuzzed automatically.",
-            Error::Empty => "It is useless to fuzz function without input parameters.",
-            Error::ComplexArg => "Type of the function must be either standalone, or borrowed standalone (like `&Type`, but not like `&(u32, String)`)",
-            Error::ComplexSelfType => "Only implementations for simple (like `MyType`) types are supported",
-            Error::MultipleRes => "Muptiple Self values in function args.",
-            Error::ComplexMethodCall => "Complex method calls are not currently supported.",
-            Error::ComplexVariable => "Complex variables (like `&mut *a`) are not supported",
-        };
+/// Statements, which run the setup hook on the head of `data`, along with the `Unstructured`
+/// holding the rest of it. The hook runs once per input, before anything is decoded.
+fn prepared_input(
+    data: TokenStream,
+    arbitrary_crate: &Ident,
+    setup: Option<&Path>,
+    bail: TokenStream,
+) -> (TokenStream, TokenStream) {
+    match setup {
+        Some(setup) => (
+            quote! {
+                let mut u = ::#arbitrary_crate::Unstructured::new(#data);
+                if #setup(&mut u).is_err() {
+                    #bail;
+                }
+            },
+            quote!(u),
+        ),
+        None => (
+            TokenStream::new(),
+            quote!(::#arbitrary_crate::Unstructured::new(#data)),
+        ),
+    }
+}
 
-        write!(f, "{}", err_msg)
+/// Bytes, which a raw byte slice function takes, after the setup hook consumed its part.
+fn raw_input(input: &TokenStream, setup: Option<&Path>) -> TokenStream {
+    match setup {
+        Some(_) => quote!(#input.take_rest()),
+        None => quote!(data),
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use assert_tokens_eq::assert_tokens_eq;
-    use pretty_assertions::assert_eq;
-    use syn::ItemImpl;
+pub fn fuzz_debug_entry(
+    signature: &Signature,
+    impl_type: Option<&Type>,
+    module_path: &TokenStream,
+    crate_ident: &Ident,
+    arbitrary_crate: &Ident,
+    setup: Option<&Path>,
+) -> Result<ItemFn, Error> {
+    // Lets tools outside of the user's crate show, what a raw input decodes to.
+    let ident = fuzz_ident(signature, impl_type, module_path)?;
+    let debug_entry_ident = debug_entry_ident(crate_ident, &ident);
+    let arg_type = format_ident!("__fuzz_struct_{}", ident);
+    let (prepare, input) =
+        prepared_input(quote!(data), arbitrary_crate, setup, quote!(return None));
 
-    #[test]
-    fn struct_no_borrows() {
-        let function: ItemFn = syn::parse2(quote! {
-            pub fn maybe_checked_mul(a: u64, b: u64, crash_on_overflow: bool) -> u64 {
-                if crash_on_overflow {
-                    a.checked_mul(b).expect("Overflow has occurred")
-                } else {
-                    a.overflowing_mul(b).0
-                }
+    if is_raw(signature) {
+        let data = raw_input(&input, setup);
+        return Ok(syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn #debug_entry_ident(data: &[u8]) -> Option<String> {
+                #prepare
+                Some(format!("{:?}", #data))
             }
         })
-        .unwrap();
+        .unwrap());
+    }
 
-        let fuzz_struct_needed: ItemStruct = syn::parse2(quote! {
-            #[derive(Arbitrary)]
-            #[derive(Debug)]
-            pub struct __fuzz_struct_maybe_checked_mul {
-                a: u64,
-                b: u64,
-                crash_on_overflow: bool
+    Ok(syn::parse2(quote! {
+        #[allow(non_snake_case)]
+        pub fn #debug_entry_ident(data: &[u8]) -> Option<String> {
+            #prepare
+            <#arg_type as ::#arbitrary_crate::Arbitrary>::arbitrary_take_rest(
+                #input,
+            )
+            .ok()
+            .map(|input| format!("{:?}", input))
+        }
+    })
+    .unwrap())
+}
+
+pub fn fuzz_property_test(
+    signature: &Signature,
+    impl_type: Option<&Type>,
+    module_path: &TokenStream,
+    arbitrary_crate: &Ident,
+    setup: Option<&Path>,
+) -> Result<ItemFn, Error> {
+    // Buffers come from a xorshift PRNG with a fixed seed, so failures are reproducible and
+    // no extra dependencies are required.
+    let ident = fuzz_ident(signature, impl_type, module_path)?;
+    let test_ident = format_ident!("__fuzz_property_{}", ident);
+    let arg_type = format_ident!("__fuzz_struct_{}", ident);
+    let function_ident = format_ident!("__fuzz_{}", ident);
+    let (prepare, input) = prepared_input(quote!(&data), arbitrary_crate, setup, quote!(continue));
+    let call = if is_raw(signature) {
+        let data = match setup {
+            Some(_) => quote!(#input.take_rest()),
+            None => quote!(&data),
+        };
+        quote! {
+            #prepare
+            #function_ident(#data);
+        }
+    } else {
+        quote! {
+            #prepare
+            if let Ok(input) = <#arg_type as ::#arbitrary_crate::Arbitrary>::arbitrary_take_rest(
+                #input,
+            ) {
+                #function_ident(input);
             }
-        })
-        .unwrap();
-        assert_eq!(fuzz_struct(&function.sig, None), Ok(fuzz_struct_needed));
-    }
+        }
+    };
 
-    #[test]
-    fn struct_borrowed() {
-        let function: ItemFn = syn::parse2(quote! {
-            pub fn maybe_checked_mul_borrowed(a: &mut u64, b: u64, crash_on_overflow: bool) {
-                if crash_on_overflow {
-                    *a = a.checked_mul(b).expect("Overflow has occurred");
-                } else {
-                    *a = a.overflowing_mul(b).0;
-                }
+    Ok(syn::parse2(quote! {
+        #[test]
+        #[allow(non_snake_case)]
+        fn #test_ident() {
+            let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+            let mut next = move || {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state
+            };
+            let mut data = Vec::new();
+            for _ in 0..4096 {
+                let len = (next() % 256) as usize;
+                data.clear();
+                data.extend((0..len).map(|_| next() as u8));
+                #call
             }
+        }
+    })
+    .unwrap())
+}
+
+/// Statement, which records arguments of a call as a seed for the target. Argument types are only
+/// known to the compiler, so calls with arguments lacking a `Seed` impl are silently skipped.
+pub fn seed_recorder(
+    signature: &Signature,
+    impl_type: Option<&Type>,
+    module_path: &TokenStream,
+    seed_dir: &str,
+) -> Result<Stmt, Error> {
+    let ident = fuzz_ident(signature, impl_type, module_path)?;
+    let args = signature
+        .inputs
+        .iter()
+        .map(|input| match input {
+            FnArg::Receiver(_) => Ok(quote!(self)),
+            FnArg::Typed(typed) => match &*typed.pat {
+                Pat::Ident(pat) => {
+                    let arg = &pat.ident;
+                    Ok(quote!(#arg))
+                }
+                _ => Err(Error::ComplexVariable),
+            },
         })
-        .unwrap();
+        .collect::<Result<Vec<_>, _>>()?;
 
-        let fuzz_struct_needed: ItemStruct = syn::parse2(quote! {
-            #[derive(Arbitrary)]
-            #[derive(Debug)]
-            pub struct __fuzz_struct_maybe_checked_mul_borrowed {
-                a: Box<u64>,
-                b: u64,
-                crash_on_overflow: bool
+    if is_raw(signature) {
+        // The fuzzer's input is the slice itself
+        let arg = &args[0];
+        return Ok(syn::parse2(quote! {
+            #[cfg(auto_fuzz_seeds)]
+            {
+                let mut encoder = ::auto_fuzz_test_runtime::seed::Encoder::new();
+                encoder.bytes(#arg);
+                ::auto_fuzz_test_runtime::seed::record(#ident, #seed_dir, encoder);
             }
         })
-        .unwrap();
-        assert_eq!(fuzz_struct(&function.sig, None), Ok(fuzz_struct_needed));
+        .unwrap());
     }
 
-    #[test]
-    fn struct_method_borrowed() {
-        let function: ItemFn = syn::parse2(quote! {
-            pub fn set_b(&mut self, b: u64) {
-                self.b = b;
+    Ok(syn::parse2(quote! {
+        #[cfg(auto_fuzz_seeds)]
+        {
+            use ::auto_fuzz_test_runtime::seed::{EncodeSeed as _, NoSeed as _};
+            let mut encoder = ::auto_fuzz_test_runtime::seed::Encoder::new();
+            if true #(&& (&::auto_fuzz_test_runtime::seed::Probe(&#args)).encode_seed(&mut encoder))* {
+                ::auto_fuzz_test_runtime::seed::record(#ident, #seed_dir, encoder);
             }
-        })
-        .unwrap();
-
-        let implementation: ItemImpl = syn::parse2(quote! {
+        }
+    })
+    .unwrap())
+}
+
+/// Statement, which stops seed recording for the rest of the enclosing block.
+pub fn seed_pause() -> Stmt {
+    syn::parse2(quote! {
+        #[cfg(auto_fuzz_seeds)]
+        let _paused = ::auto_fuzz_test_runtime::seed::pause();
+    })
+    .unwrap()
+}
+
+pub fn fuzz_harness(
+    signature: &Signature,
+    impl_type: Option<&Type>,
+    crate_ident: &Ident,
+    attr: &TokenStream,
+    backend: Backend,
+) -> Result<TokenStream, Error> {
+    // Idents generation
+    let ident = fuzz_ident(signature, impl_type, attr)?;
+    let entry_ident = entry_ident(crate_ident, &ident);
+
+    Ok(match backend {
+        Backend::LibFuzzer => quote!(
+            // Autogenerated fuzzing harness.
+            #![no_main]
+            use libfuzzer_sys::fuzz_target;
+            extern crate #crate_ident;
+
+            fuzz_target!(|data: &[u8]| #crate_ident::__auto_fuzz::#entry_ident(data));
+        ),
+        Backend::Honggfuzz => quote!(
+            // Autogenerated fuzzing harness.
+            use honggfuzz::fuzz;
+            extern crate #crate_ident;
+
+            fn main() {
+                loop {
+                    fuzz!(|data: &[u8]| {
+                        #crate_ident::__auto_fuzz::#entry_ident(data)
+                    });
+                }
+            }
+        ),
+        Backend::Afl => quote!(
+            // Autogenerated fuzzing harness.
+            extern crate #crate_ident;
+
+            fn main() {
+                afl::fuzz!(|data: &[u8]| {
+                    #crate_ident::__auto_fuzz::#entry_ident(data)
+                });
+            }
+        ),
+    })
+}
+
+pub fn regression_generator(crate_ident: &Ident, targets: &[String]) -> TokenStream {
+    // Crashes are decoded at runtime, as only the user's crate knows how to print them.
+    let crate_name = crate_ident.to_string();
+    let entry_prefix = entry_ident(crate_ident, "").to_string();
+    let debug_entry_idents = targets
+        .iter()
+        .map(|target| debug_entry_ident(crate_ident, target))
+        .collect::<Vec<_>>();
+
+    quote!(
+        // Autogenerated generator of regression tests.
+        extern crate #crate_ident;
+
+        use std::fmt::Write;
+        use std::fs;
+        use std::path::Path;
+
+        const TARGETS: &[(&str, fn(&[u8]) -> Option<String>)] = &[
+            #(
+                (#targets, #crate_ident::__auto_fuzz::#debug_entry_idents),
+            )*
+        ];
+
+        #[test]
+        #[ignore]
+        fn generate_regressions() {
+            let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+            let mut tests = String::new();
+            for &(target, debug) in TARGETS {
+                let artifacts = root.join("artifacts").join(target);
+                let mut crashes = match fs::read_dir(&artifacts) {
+                    Ok(entries) => entries
+                        .filter_map(|entry| entry.ok())
+                        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                        .filter(|name| name.starts_with("crash-"))
+                        .collect::<Vec<_>>(),
+                    Err(_) => continue,
+                };
+                if crashes.is_empty() {
+                    continue;
+                }
+                crashes.sort();
+
+                for crash in crashes {
+                    let data = fs::read(artifacts.join(&crash)).unwrap();
+                    let input = debug(&data)
+                        .unwrap_or_else(|| String::from("<input is not decodable>"));
+                    let test = crash.replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+
+                    writeln!(tests).unwrap();
+                    writeln!(tests, "#[test]").unwrap();
+                    writeln!(tests, "fn {}__{}() {{", target, test).unwrap();
+                    for line in input.lines() {
+                        writeln!(tests, "    // {}", line).unwrap();
+                    }
+                    writeln!(
+                        tests,
+                        "    let data = include_bytes!(\"../artifacts/{}/{}\");",
+                        target, crash
+                    )
+                    .unwrap();
+                    writeln!(
+                        tests,
+                        "    {}::__auto_fuzz::{}{}(data);",
+                        #crate_name, #entry_prefix, target
+                    )
+                    .unwrap();
+                    writeln!(tests, "}}").unwrap();
+                }
+            }
+
+            fs::write(
+                root.join("tests").join("regressions.rs"),
+                format!(
+                    "// Autogenerated from crash artifacts by `generate_regressions`.\n#![allow(non_snake_case)]\n{}",
+                    tests
+                ),
+            )
+            .unwrap();
+        }
+    )
+}
+
+pub fn corpus_replay(crate_ident: &Ident, targets: &[String]) -> TokenStream {
+    let entry_idents = targets
+        .iter()
+        .map(|target| entry_ident(crate_ident, target))
+        .collect::<Vec<_>>();
+    let debug_entry_idents = targets
+        .iter()
+        .map(|target| debug_entry_ident(crate_ident, target))
+        .collect::<Vec<_>>();
+
+    quote!(
+        // Autogenerated replay of fuzzing corpora.
+        extern crate #crate_ident;
+
+        use std::env;
+        use std::fs;
+        use std::path::Path;
+
+        const TARGETS: &[(&str, fn(&[u8]), fn(&[u8]) -> Option<String>)] = &[
+            #(
+                (
+                    #targets,
+                    #crate_ident::__auto_fuzz::#entry_idents,
+                    #crate_ident::__auto_fuzz::#debug_entry_idents,
+                ),
+            )*
+        ];
+
+        #[test]
+        fn replay_corpus() {
+            let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+            // Used by `cargo auto-fuzz coverage` to collect a profile per target.
+            let only = env::var("AUTO_FUZZ_REPLAY_TARGET").ok();
+            for &(target, entry, _) in TARGETS {
+                if matches!(&only, Some(only) if only != target) {
+                    continue;
+                }
+                let corpus = match fs::read_dir(root.join("corpus").join(target)) {
+                    Ok(entries) => entries,
+                    Err(_) => continue,
+                };
+                for input in corpus.filter_map(|entry| entry.ok()) {
+                    let path = input.path();
+                    if !path.is_file() {
+                        continue;
+                    }
+                    // Output is shown only for a failed test, so it points to the offending input.
+                    println!("Replaying {}", path.display());
+                    let data = fs::read(&path).unwrap();
+                    entry(&data);
+                }
+            }
+        }
+
+        // Used by `cargo auto-fuzz triage` to replay a single input.
+        #[test]
+        #[ignore]
+        fn replay_input() {
+            let target = env::var("AUTO_FUZZ_REPLAY_TARGET").unwrap();
+            let path = env::var("AUTO_FUZZ_REPLAY_INPUT").unwrap();
+            let &(_, entry, debug) = TARGETS
+                .iter()
+                .find(|(name, _, _)| *name == target)
+                .expect("Unknown fuzzing target");
+            let data = fs::read(&path).unwrap();
+            if let Some(input) = debug(&data) {
+                println!("AUTO_FUZZ_INPUT: {}", input);
+            }
+            entry(&data);
+        }
+    )
+}
+
+pub fn entry_ident(crate_ident: &Ident, ident: &str) -> Ident {
+    format_ident!("__auto_fuzz_{}__{}", crate_ident, ident)
+}
+
+pub fn debug_entry_ident(crate_ident: &Ident, ident: &str) -> Ident {
+    format_ident!("__auto_fuzz_debug__{}__{}", crate_ident, ident)
+}
+
+/// Entry points of harnesses in a module of the user's crate.
+pub struct HubEntries {
+    /// `cfg` attributes of the module and the fuzzed item.
+    pub cfgs: Vec<Attribute>,
+    pub module_path: TokenStream,
+    pub idents: Vec<Ident>,
+}
+
+/// Module at the crate root, which re-exports entry points, so the fuzzing crate calls them by
+/// path, whatever the visibility of modules with fuzzed functions.
+pub fn entry_hub(entries: &[HubEntries]) -> TokenStream {
+    let uses = entries.iter().map(|entries| {
+        let cfgs = &entries.cfgs;
+        let idents = &entries.idents;
+        let module_path = if entries.module_path.is_empty() {
+            quote!(crate)
+        } else {
+            let module_path = &entries.module_path;
+            quote!(crate::#module_path)
+        };
+        quote! {
+            #(#cfgs)*
+            pub use #module_path::{#(#idents),*};
+        }
+    });
+    quote! {
+        #[doc(hidden)]
+        pub mod __auto_fuzz {
+            #(#uses)*
+        }
+    }
+}
+
+fn fuzz_ident(
+    signature: &Signature,
+    impl_type: Option<&Type>,
+    module_path: &TokenStream,
+) -> Result<String, Error> {
+    construct_harness_ident(&signature.ident, impl_type, module_path)
+        .map_err(|_| Error::ComplexSelfType)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    Unsafe,
+    ComplexArg,
+    ComplexSelfType,
+    MultipleRes,
+    ComplexMethodCall,
+    ComplexVariable,
+    Constraint(String),
+    /// Path of another function, whose harness has the same name.
+    DuplicateName(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let err_msg = match self {
+            Error::Unsafe => "unsafe functions can not be fuzzed without `unsafe_precondition`.",
+            Error::ComplexArg => "Type of the function must be either standalone, or borrowed standalone (like `&Type`, but not like `&(u32, String)`)",
+            Error::ComplexSelfType => "Only implementations for simple (like `MyType`) types are supported",
+            Error::MultipleRes => "Muptiple Self values in function args.",
+            Error::ComplexMethodCall => "Complex method calls are not currently supported.",
+            Error::ComplexVariable => "Complex variables (like `&mut *a`) are not supported",
+            Error::Constraint(error) => {
+                return write!(f, "Invalid `#[fuzz(...)]` attribute: {}", error)
+            }
+            Error::DuplicateName(other) => {
+                return write!(
+                    f,
+                    "Fuzzing harness has the same name as the one of `{}`, rename one of them.",
+                    other
+                )
+            }
+        };
+
+        write!(f, "{}", err_msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_tokens_eq::assert_tokens_eq;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn struct_no_borrows() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn maybe_checked_mul(a: u64, b: u64, crash_on_overflow: bool) -> u64 {
+                if crash_on_overflow {
+                    a.checked_mul(b).expect("Overflow has occurred")
+                } else {
+                    a.overflowing_mul(b).0
+                }
+            }
+        })
+        .unwrap();
+
+        let fuzz_struct_needed: ItemStruct = syn::parse2(quote! {
+            #[derive(Debug)]
+            #[allow(non_camel_case_types)]
+            pub struct __fuzz_struct_maybe_checked_mul {
+                a: u64,
+                b: u64,
+                crash_on_overflow: bool
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            fuzz_struct(&function.sig, None, &TokenStream::new()),
+            Ok(fuzz_struct_needed)
+        );
+    }
+
+    #[test]
+    fn struct_borrowed() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn maybe_checked_mul_borrowed(a: &mut u64, b: u64, crash_on_overflow: bool) {
+                if crash_on_overflow {
+                    *a = a.checked_mul(b).expect("Overflow has occurred");
+                } else {
+                    *a = a.overflowing_mul(b).0;
+                }
+            }
+        })
+        .unwrap();
+
+        let fuzz_struct_needed: ItemStruct = syn::parse2(quote! {
+            #[derive(Debug)]
+            #[allow(non_camel_case_types)]
+            pub struct __fuzz_struct_maybe_checked_mul_borrowed {
+                a: Box<u64>,
+                b: u64,
+                crash_on_overflow: bool
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            fuzz_struct(&function.sig, None, &TokenStream::new()),
+            Ok(fuzz_struct_needed)
+        );
+    }
+
+    #[test]
+    fn struct_method_borrowed() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn set_b(&mut self, b: u64) {
+                self.b = b;
+            }
+        })
+        .unwrap();
+
+        let implementation: ItemImpl = syn::parse2(quote! {
+            impl TestStruct {
+            }
+        })
+        .unwrap();
+
+        let fuzz_struct_needed: ItemStruct = syn::parse2(quote! {
+            #[derive(Debug)]
+            #[allow(non_camel_case_types)]
+            pub struct __fuzz_struct_TestStruct__set_b {
+                slf: Box<TestStruct>,
+                b: u64
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            fuzz_struct(
+                &function.sig,
+                Some(&implementation.self_ty),
+                &TokenStream::new()
+            ),
+            Ok(fuzz_struct_needed)
+        );
+    }
+
+    #[test]
+    fn struct_method_unborrowed() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn set_b(self, b: u64) -> u64 {
+                self.b + b
+            }
+        })
+        .unwrap();
+        let implementation: ItemImpl = syn::parse2(quote! {
+            impl TestStruct {
+            }
+        })
+        .unwrap();
+
+        let fuzz_struct_needed: ItemStruct = syn::parse2(quote! {
+            #[derive(Debug)]
+            #[allow(non_camel_case_types)]
+            pub struct __fuzz_struct_TestStruct__set_b {
+                slf: TestStruct,
+                b: u64
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            fuzz_struct(
+                &function.sig,
+                Some(&implementation.self_ty),
+                &TokenStream::new()
+            ),
+            Ok(fuzz_struct_needed)
+        );
+    }
+
+    #[test]
+    fn struct_method_module() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn set_b(&mut self, b: u64) {
+                self.b = b;
+            }
+        })
+        .unwrap();
+
+        let implementation: ItemImpl = syn::parse2(quote! {
             impl TestStruct {
             }
         })
         .unwrap();
 
-        let fuzz_struct_needed: ItemStruct = syn::parse2(quote! {
-            #[derive(Arbitrary)]
-            #[derive(Debug)]
-            pub struct __fuzz_struct_TestStruct_set_b {
-                slf: Box<TestStruct>,
-                b: u64
+        let fuzz_struct_needed: ItemStruct = syn::parse2(quote! {
+            #[derive(Debug)]
+            #[allow(non_camel_case_types)]
+            pub struct __fuzz_struct_foo__bar__TestStruct__set_b {
+                slf: Box<TestStruct>,
+                b: u64
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            fuzz_struct(
+                &function.sig,
+                Some(&implementation.self_ty),
+                &quote!(foo::bar)
+            ),
+            Ok(fuzz_struct_needed)
+        );
+    }
+
+    #[test]
+    fn struct_sliced_arg() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn maybe_checked_mul(a: u64, b: u64, crash_on_overflow: bool, sl: &[u32]) -> u64 {
+                if crash_on_overflow {
+                    a.checked_mul(b).expect("Overflow has occurred")
+                } else {
+                    a.overflowing_mul(b).0
+                }
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            fuzz_struct(&function.sig, None, &TokenStream::new()),
+            Err(Error::ComplexArg)
+        );
+    }
+
+    #[test]
+    fn struct_complex_variable() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn maybe_checked_mul(a: u64, b: u64, (c,d):(u32,u64)) -> u64 {
+                if crash_on_overflow {
+                    a.checked_mul(b).expect("Overflow has occurred")
+                } else {
+                    a.overflowing_mul(b).0
+                }
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            fuzz_struct(&function.sig, None, &TokenStream::new()),
+            Err(Error::ComplexVariable)
+        );
+    }
+
+    #[test]
+    fn arbitrary_impl() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn maybe_checked_mul_borrowed(a: &mut u64, b: u64, crash_on_overflow: bool) {
+                if crash_on_overflow {
+                    *a = a.checked_mul(b).expect("Overflow has occurred");
+                } else {
+                    *a = a.overflowing_mul(b).0;
+                }
+            }
+        })
+        .unwrap();
+
+        let arbitrary_impl_needed: ItemImpl = syn::parse2(quote! {
+            impl<'a> ::fuzz_input::Arbitrary<'a> for __fuzz_struct_maybe_checked_mul_borrowed {
+                fn arbitrary(
+                    u: &mut ::fuzz_input::Unstructured<'a>,
+                ) -> ::fuzz_input::Result<Self> {
+                    Ok(__fuzz_struct_maybe_checked_mul_borrowed {
+                        a: ::fuzz_input::Arbitrary::arbitrary(u)?,
+                        b: ::fuzz_input::Arbitrary::arbitrary(u)?,
+                        crash_on_overflow: ::fuzz_input::Arbitrary::arbitrary(u)?
+                    })
+                }
+            }
+        })
+        .unwrap();
+        let fuzz_struct = fuzz_struct(&function.sig, None, &TokenStream::new()).unwrap();
+        assert_eq!(
+            fuzz_arbitrary_impl(&fuzz_struct, &function.sig, &format_ident!("fuzz_input"),),
+            Ok(arbitrary_impl_needed)
+        );
+    }
+
+    #[test]
+    fn arbitrary_impl_adapted() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn connect(
+                addr: std::net::IpAddr,
+                retries: &NonZeroU8,
+                deadline: SystemTime,
+                timeout: Duration,
+                other: my::NonZeroU8,
+                compare: impl Fn(&f64, &f64) -> Ordering,
+            ) {}
+        })
+        .unwrap();
+
+        let arbitrary_impl_needed: ItemImpl = syn::parse2(quote! {
+            impl<'a> ::arbitrary::Arbitrary<'a> for __fuzz_struct_connect {
+                fn arbitrary(
+                    u: &mut ::arbitrary::Unstructured<'a>,
+                ) -> ::arbitrary::Result<Self> {
+                    Ok(__fuzz_struct_connect {
+                        addr: {
+                            let value: ::auto_fuzz_test_runtime::adapter::Adapter<std::net::IpAddr> =
+                                ::arbitrary::Arbitrary::arbitrary(u)?;
+                            ::std::convert::Into::into(value.0)
+                        },
+                        retries: {
+                            let value: ::auto_fuzz_test_runtime::adapter::Adapter<NonZeroU8> =
+                                ::arbitrary::Arbitrary::arbitrary(u)?;
+                            ::std::convert::Into::into(value.0)
+                        },
+                        deadline: {
+                            let value: ::auto_fuzz_test_runtime::adapter::Adapter<SystemTime> =
+                                ::arbitrary::Arbitrary::arbitrary(u)?;
+                            ::std::convert::Into::into(value.0)
+                        },
+                        timeout: ::arbitrary::Arbitrary::arbitrary(u)?,
+                        other: ::arbitrary::Arbitrary::arbitrary(u)?,
+                        compare: ::auto_fuzz_test_runtime::closure::ReturnTable::arbitrary_with(
+                            u,
+                            |u| {
+                                let value: ::auto_fuzz_test_runtime::adapter::Adapter<Ordering> =
+                                    ::arbitrary::Arbitrary::arbitrary(u)?;
+                                Ok(value.0)
+                            },
+                        )?
+                    })
+                }
+            }
+        })
+        .unwrap();
+        let fuzz_struct = fuzz_struct(&function.sig, None, &TokenStream::new()).unwrap();
+        assert_tokens_eq!(
+            fuzz_arbitrary_impl(&fuzz_struct, &function.sig, &format_ident!("arbitrary")).unwrap(),
+            arbitrary_impl_needed
+        );
+    }
+
+    #[test]
+    fn arbitrary_impl_constrained() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn parse_number(
+                #[fuzz(max_len = 16)] digits: &str,
+                #[fuzz(range = 2..=36)] radix: u32,
+                #[fuzz(range = ..8)] shift: &u8,
+                #[fuzz(range = 1..LIMIT)] count: usize,
+                #[fuzz(max_len = LIMIT)] bytes: Vec<u8>,
+                #[fuzz(with = gen::config)] config: &Config,
+                #[fuzz(grammar = "json")] json: String,
+                #[fuzz(grammar_file = "fuzz/query.gram")] query: &str,
+            ) {}
+        })
+        .unwrap();
+
+        let arbitrary_impl_needed: ItemImpl = syn::parse2(quote! {
+            impl<'a> ::arbitrary::Arbitrary<'a> for __fuzz_struct_parse_number {
+                fn arbitrary(
+                    u: &mut ::arbitrary::Unstructured<'a>,
+                ) -> ::arbitrary::Result<Self> {
+                    Ok(__fuzz_struct_parse_number {
+                        digits: {
+                            let mut value: ::std::string::String =
+                                ::arbitrary::Arbitrary::arbitrary(u)?;
+                            let mut len = value.len().min(16);
+                            while !value.is_char_boundary(len) {
+                                len -= 1;
+                            }
+                            value.truncate(len);
+                            ::std::convert::Into::into(value)
+                        },
+                        radix: {
+                            let start: u32 = 2;
+                            let end: u32 = 36;
+                            let value: u32 = u.int_in_range(start..=end)?;
+                            ::std::convert::Into::into(value)
+                        },
+                        shift: {
+                            let start: u8 = <u8>::MIN;
+                            let end: u8 = match <u8>::checked_sub(8, 1) {
+                                Some(end) => end,
+                                None => return Err(::arbitrary::Error::IncorrectFormat),
+                            };
+                            let value: u8 = u.int_in_range(start..=end)?;
+                            ::std::convert::Into::into(value)
+                        },
+                        count: {
+                            let start: usize = 1;
+                            let end: usize = match <usize>::checked_sub(LIMIT, 1) {
+                                Some(end) => end,
+                                None => return Err(::arbitrary::Error::IncorrectFormat),
+                            };
+                            if start > end {
+                                return Err(::arbitrary::Error::IncorrectFormat);
+                            }
+                            let value: usize = u.int_in_range(start..=end)?;
+                            ::std::convert::Into::into(value)
+                        },
+                        bytes: {
+                            let mut value: Vec<u8> = ::arbitrary::Arbitrary::arbitrary(u)?;
+                            value.truncate(LIMIT);
+                            ::std::convert::Into::into(value)
+                        },
+                        config: {
+                            let value = gen::config(u)?;
+                            ::std::convert::Into::into(value)
+                        },
+                        json: {
+                            let value: ::std::string::String =
+                                ::auto_fuzz_test_runtime::grammar::JSON.generate(u)?;
+                            ::std::convert::Into::into(value)
+                        },
+                        query: {
+                            let value: ::std::string::String = {
+                                static GRAMMAR: ::auto_fuzz_test_runtime::grammar::LazyGrammar =
+                                    ::auto_fuzz_test_runtime::grammar::LazyGrammar::new(include_str!(
+                                        concat!(env!("CARGO_MANIFEST_DIR"), "/", "fuzz/query.gram")
+                                    ));
+                                &GRAMMAR
+                            }
+                            .generate(u)?;
+                            ::std::convert::Into::into(value)
+                        }
+                    })
+                }
+            }
+        })
+        .unwrap();
+        let fuzz_struct = fuzz_struct(&function.sig, None, &TokenStream::new()).unwrap();
+        assert_tokens_eq!(
+            fuzz_arbitrary_impl(&fuzz_struct, &function.sig, &format_ident!("arbitrary")).unwrap(),
+            arbitrary_impl_needed
+        );
+
+        let function: ItemFn = syn::parse2(quote!(
+            pub fn f(#[fuzz(range = 36)] radix: u32) {}
+        ))
+        .unwrap();
+        let invalid_struct = super::fuzz_struct(&function.sig, None, &TokenStream::new()).unwrap();
+        assert!(matches!(
+            fuzz_arbitrary_impl(&invalid_struct, &function.sig, &format_ident!("arbitrary")),
+            Err(Error::Constraint(_))
+        ));
+    }
+
+    #[test]
+    fn function_unborrowed() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn maybe_checked_mul(a: u64, b: u64, crash_on_overflow: bool) -> u64 {
+                if crash_on_overflow {
+                    a.checked_mul(b).expect("Overflow has occurred")
+                } else {
+                    a.overflowing_mul(b).0
+                }
+            }
+        })
+        .unwrap();
+
+        let fuzz_function_needed: ItemFn = syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn __fuzz_maybe_checked_mul(mut input:__fuzz_struct_maybe_checked_mul) {
+                maybe_checked_mul(input.a, input.b, input.crash_on_overflow);
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            fuzz_function(&function.sig, None, &TokenStream::new(), None, None),
+            Ok(fuzz_function_needed)
+        );
+    }
+
+    #[test]
+    fn function_borrowed() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn maybe_checked_mul_borrowed(a: &mut u64, b: u64, crash_on_overflow: bool) {
+                if crash_on_overflow {
+                    *a = a.checked_mul(b).expect("Overflow has occurred");
+                } else {
+                    *a = a.overflowing_mul(b).0;
+                }
+            }
+        })
+        .unwrap();
+
+        let fuzz_function_needed: ItemFn = syn::parse2(
+            quote! {
+                #[allow(non_snake_case)]
+                pub fn __fuzz_maybe_checked_mul_borrowed(mut input:__fuzz_struct_maybe_checked_mul_borrowed) {
+                    maybe_checked_mul_borrowed(&mut *input.a, input.b, input.crash_on_overflow);
+                }
+            }
+        ).unwrap();
+        assert_eq!(
+            fuzz_function(&function.sig, None, &TokenStream::new(), None, None),
+            Ok(fuzz_function_needed)
+        );
+    }
+
+    #[test]
+    fn function_sliced_arg() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn maybe_checked_mul(a: u64, b: u64, crash_on_overflow: bool, sl: &[u32]) -> u64 {
+                if crash_on_overflow {
+                    a.checked_mul(b).expect("Overflow has occurred")
+                } else {
+                    a.overflowing_mul(b).0
+                }
+            }
+        })
+        .unwrap();
+
+        let fuzz_function_needed: ItemFn = syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn __fuzz_maybe_checked_mul(mut input:__fuzz_struct_maybe_checked_mul) {
+                maybe_checked_mul(input.a, input.b, input.crash_on_overflow, & *input.sl);
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            fuzz_function(&function.sig, None, &TokenStream::new(), None, None),
+            Ok(fuzz_function_needed)
+        );
+    }
+
+    #[test]
+    fn function_complex_variable() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn maybe_checked_mul(a: u64, b: u64, (c,d):(u32,u64)) -> u64 {
+                if crash_on_overflow {
+                    a.checked_mul(b).expect("Overflow has occurred")
+                } else {
+                    a.overflowing_mul(b).0
+                }
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            fuzz_function(&function.sig, None, &TokenStream::new(), None, None),
+            Err(Error::ComplexVariable)
+        );
+    }
+
+    #[test]
+    fn function_unsafe() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub unsafe fn first_char(bytes: &[u8], index: usize) -> char {
+                std::str::from_utf8_unchecked(bytes).chars().nth(index).unwrap_or_default()
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            fuzz_function(&function.sig, None, &TokenStream::new(), None, None),
+            Err(Error::Unsafe)
+        );
+
+        let fuzz_function_needed: ItemFn = syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn __fuzz_first_char(mut input: __fuzz_struct_first_char) {
+                if is_valid(&*input.bytes, &input.index) {
+                    unsafe { first_char(&*input.bytes, input.index) };
+                }
+            }
+        })
+        .unwrap();
+        let precondition: Path = syn::parse2(quote!(is_valid)).unwrap();
+        assert_tokens_eq!(
+            fuzz_function(
+                &function.sig,
+                None,
+                &TokenStream::new(),
+                None,
+                Some(&precondition)
+            )
+            .unwrap(),
+            fuzz_function_needed
+        );
+
+        let function: ItemFn = syn::parse2(quote! {
+            pub unsafe fn parse(data: &[u8]) {}
+        })
+        .unwrap();
+        let fuzz_function_needed: ItemFn = syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn __fuzz_parse(data: &[u8]) {
+                if checks::is_utf8(data) {
+                    unsafe { parse(data) };
+                }
+            }
+        })
+        .unwrap();
+        let precondition: Path = syn::parse2(quote!(checks::is_utf8)).unwrap();
+        assert_tokens_eq!(
+            fuzz_function(
+                &function.sig,
+                None,
+                &TokenStream::new(),
+                None,
+                Some(&precondition)
+            )
+            .unwrap(),
+            fuzz_function_needed
+        );
+    }
+
+    #[test]
+    fn function_async() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub async fn handle(request: &str, retries: u8) -> usize {
+                request.len() + retries as usize
+            }
+        })
+        .unwrap();
+
+        let fuzz_function_needed: ItemFn = syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn __fuzz_handle(mut input: __fuzz_struct_handle) {
+                {
+                    struct ThreadWaker(::std::thread::Thread);
+                    impl ::std::task::Wake for ThreadWaker {
+                        fn wake(self: ::std::sync::Arc<Self>) {
+                            self.0.unpark();
+                        }
+                    }
+                    let waker = ::std::task::Waker::from(::std::sync::Arc::new(ThreadWaker(
+                        ::std::thread::current(),
+                    )));
+                    let mut context = ::std::task::Context::from_waker(&waker);
+                    let mut future = ::std::boxed::Box::pin(handle(&*input.request, input.retries));
+                    while ::std::future::Future::poll(future.as_mut(), &mut context).is_pending() {
+                        ::std::thread::park();
+                    }
+                }
+            }
+        })
+        .unwrap();
+        assert_tokens_eq!(
+            fuzz_function(&function.sig, None, &TokenStream::new(), None, None).unwrap(),
+            fuzz_function_needed
+        );
+
+        let fuzz_function_needed: ItemFn = syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn __fuzz_handle(mut input: __fuzz_struct_handle) {
+                pollster::block_on(handle(&*input.request, input.retries));
+            }
+        })
+        .unwrap();
+        let executor: Path = syn::parse2(quote!(pollster::block_on)).unwrap();
+        assert_tokens_eq!(
+            fuzz_function(
+                &function.sig,
+                None,
+                &TokenStream::new(),
+                Some(&executor),
+                None
+            )
+            .unwrap(),
+            fuzz_function_needed
+        );
+    }
+
+    #[test]
+    fn function_empty() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn reload_config() {
+                CONFIG.with(|config| config.borrow_mut().reload());
+            }
+        })
+        .unwrap();
+        let fuzz_function_needed: ItemFn = syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn __fuzz_reload_config(_input: __fuzz_struct_reload_config) {
+                reload_config();
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            fuzz_function(&function.sig, None, &TokenStream::new(), None, None),
+            Ok(fuzz_function_needed)
+        );
+
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn default() -> Self {
+                Self { a: 0, b: 0 }
+            }
+        })
+        .unwrap();
+        let impl_type: Type = syn::parse2(quote!(TestStruct)).unwrap();
+        let fuzz_function_needed: ItemFn = syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn __fuzz_TestStruct__default(_input: __fuzz_struct_TestStruct__default) {
+                <TestStruct>::default();
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            fuzz_function(
+                &function.sig,
+                Some(&impl_type),
+                &TokenStream::new(),
+                None,
+                None
+            ),
+            Ok(fuzz_function_needed)
+        );
+    }
+
+    #[test]
+    fn function_closure() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn retain_count(
+                values: Vec<u32>,
+                keep: impl Fn(&u32) -> bool,
+                visit: &mut dyn FnMut(usize, u32),
+            ) -> usize {
+                values.into_iter().filter(|value| keep(value)).count()
+            }
+        })
+        .unwrap();
+
+        let fuzz_struct_needed: ItemStruct = syn::parse2(quote! {
+            #[derive(Debug)]
+            #[allow(non_camel_case_types)]
+            pub struct __fuzz_struct_retain_count {
+                values: Vec<u32>,
+                keep: ::auto_fuzz_test_runtime::closure::ReturnTable<bool>,
+                visit: ::auto_fuzz_test_runtime::closure::ReturnTable<()>
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            fuzz_struct(&function.sig, None, &TokenStream::new()),
+            Ok(fuzz_struct_needed)
+        );
+
+        let fuzz_function_needed: ItemFn = syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn __fuzz_retain_count(mut input: __fuzz_struct_retain_count) {
+                retain_count(
+                    input.values,
+                    {
+                        let table = input.keep;
+                        move |arg0| {
+                            use ::auto_fuzz_test_runtime::closure::{Args, HashArgs as _, OpaqueArgs as _};
+                            table.answer((&Args(&(&arg0,))).key())
+                        }
+                    },
+                    &mut {
+                        let table = input.visit;
+                        move |arg0, arg1| {
+                            use ::auto_fuzz_test_runtime::closure::{Args, HashArgs as _, OpaqueArgs as _};
+                            table.answer((&Args(&(&arg0, &arg1,))).key())
+                        }
+                    }
+                );
+            }
+        })
+        .unwrap();
+        assert_tokens_eq!(
+            fuzz_function(&function.sig, None, &TokenStream::new(), None, None).unwrap(),
+            fuzz_function_needed
+        );
+
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn find(values: &[u32], predicate: impl Fn(&u32) -> &u32) {}
+        })
+        .unwrap();
+        assert_eq!(
+            fuzz_struct(&function.sig, None, &TokenStream::new()),
+            Err(Error::ComplexArg)
+        );
+    }
+
+    #[test]
+    fn entry_setup() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn reload_config() {}
+        })
+        .unwrap();
+        let fuzz_entry_needed: ItemFn = syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn __auto_fuzz_lib__reload_config(data: &[u8]) {
+                let mut u = ::arbitrary::Unstructured::new(data);
+                if crate::fuzz_setup(&mut u).is_err() {
+                    return;
+                }
+                if let Ok(input) = <__fuzz_struct_reload_config as ::arbitrary::Arbitrary>::arbitrary_take_rest(
+                    u,
+                ) {
+                    __fuzz_reload_config(input);
+                }
+            }
+        })
+        .unwrap();
+        let setup: Path = syn::parse2(quote!(crate::fuzz_setup)).unwrap();
+        assert_eq!(
+            fuzz_entry(
+                &function.sig,
+                None,
+                &TokenStream::new(),
+                &format_ident!("lib"),
+                &format_ident!("arbitrary"),
+                Some(&setup)
+            ),
+            Ok(fuzz_entry_needed)
+        );
+
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn parse(data: &[u8]) {}
+        })
+        .unwrap();
+        let fuzz_debug_entry_needed: ItemFn = syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn __auto_fuzz_debug__lib__parse(data: &[u8]) -> Option<String> {
+                let mut u = ::arbitrary::Unstructured::new(data);
+                if crate::fuzz_setup(&mut u).is_err() {
+                    return None;
+                }
+                Some(format!("{:?}", u.take_rest()))
             }
         })
         .unwrap();
         assert_eq!(
-            fuzz_struct(&function.sig, Some(&implementation.self_ty)),
-            Ok(fuzz_struct_needed)
+            fuzz_debug_entry(
+                &function.sig,
+                None,
+                &TokenStream::new(),
+                &format_ident!("lib"),
+                &format_ident!("arbitrary"),
+                Some(&setup)
+            ),
+            Ok(fuzz_debug_entry_needed)
         );
     }
 
     #[test]
-    fn struct_method_unborrowed() {
+    fn method_unborrowed() {
         let function: ItemFn = syn::parse2(quote! {
             pub fn set_b(self, b: u64) -> u64 {
                 self.b + b
@@ -676,126 +2240,280 @@ mod tests {
             }
         })
         .unwrap();
-
-        let fuzz_struct_needed: ItemStruct = syn::parse2(quote! {
-            #[derive(Arbitrary)]
-            #[derive(Debug)]
-            pub struct __fuzz_struct_TestStruct_set_b {
-                slf: TestStruct,
-                b: u64
+        let fuzz_function_needed: ItemFn = syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn __fuzz_TestStruct__set_b(mut input: __fuzz_struct_TestStruct__set_b) {
+                    (input.slf).set_b(input.b);
             }
         })
         .unwrap();
         assert_eq!(
-            fuzz_struct(&function.sig, Some(&implementation.self_ty)),
-            Ok(fuzz_struct_needed)
+            fuzz_function(
+                &function.sig,
+                Some(&implementation.self_ty),
+                &TokenStream::new(),
+                None,
+                None
+            ),
+            Ok(fuzz_function_needed)
         );
     }
 
     #[test]
-    fn struct_sliced_arg() {
+    fn method_borrowed() {
         let function: ItemFn = syn::parse2(quote! {
-            pub fn maybe_checked_mul(a: u64, b: u64, crash_on_overflow: bool, sl: &[u32]) -> u64 {
-                if crash_on_overflow {
-                    a.checked_mul(b).expect("Overflow has occurred")
-                } else {
-                    a.overflowing_mul(b).0
-                }
+            pub fn set_b(&mut self, b: u64) {
+                self.b = b;
+            }
+        })
+        .unwrap();
+        let implementation: ItemImpl = syn::parse2(quote! {
+            impl TestStruct {
+            }
+        })
+        .unwrap();
+        let fuzz_function_needed: ItemFn = syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn __fuzz_TestStruct__set_b(mut input: __fuzz_struct_TestStruct__set_b) {
+                    (input.slf).set_b(input.b);
             }
         })
         .unwrap();
-        assert_eq!(fuzz_struct(&function.sig, None), Err(Error::ComplexArg));
+        assert_eq!(
+            fuzz_function(
+                &function.sig,
+                Some(&implementation.self_ty),
+                &TokenStream::new(),
+                None,
+                None
+            ),
+            Ok(fuzz_function_needed)
+        );
     }
 
     #[test]
-    fn struct_complex_variable() {
+    fn method_module() {
         let function: ItemFn = syn::parse2(quote! {
-            pub fn maybe_checked_mul(a: u64, b: u64, (c,d):(u32,u64)) -> u64 {
-                if crash_on_overflow {
-                    a.checked_mul(b).expect("Overflow has occurred")
-                } else {
-                    a.overflowing_mul(b).0
-                }
+            pub fn set_b(&mut self, b: u64) {
+                self.b = b;
+            }
+        })
+        .unwrap();
+        let implementation: ItemImpl = syn::parse2(quote! {
+            impl TestStruct {
+            }
+        })
+        .unwrap();
+        let fuzz_function_needed: ItemFn = syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn __fuzz_foo__bar__TestStruct__set_b(mut input: __fuzz_struct_foo__bar__TestStruct__set_b) {
+                    (input.slf).set_b(input.b);
             }
         })
         .unwrap();
         assert_eq!(
-            fuzz_struct(&function.sig, None),
-            Err(Error::ComplexVariable)
+            fuzz_function(
+                &function.sig,
+                Some(&implementation.self_ty),
+                &quote!(foo::bar),
+                None,
+                None
+            ),
+            Ok(fuzz_function_needed)
         );
     }
 
     #[test]
-    fn function_unborrowed() {
+    fn method_generator() {
         let function: ItemFn = syn::parse2(quote! {
-            pub fn maybe_checked_mul(a: u64, b: u64, crash_on_overflow: bool) -> u64 {
-                if crash_on_overflow {
-                    a.checked_mul(b).expect("Overflow has occurred")
-                } else {
-                    a.overflowing_mul(b).0
-                }
+            pub fn new(a:u64, b:u64) -> TestStruct {
+                TestStruct {a,b}
+            }
+        })
+        .unwrap();
+        let implementation: ItemImpl = syn::parse2(quote! {
+            impl TestStruct {
             }
         })
         .unwrap();
+        let fuzz_function_needed: ItemFn = syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn __fuzz_TestStruct__new(mut input: __fuzz_struct_TestStruct__new) {
+                <TestStruct>::new(input.a, input.b);
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            fuzz_function(
+                &function.sig,
+                Some(&implementation.self_ty),
+                &TokenStream::new(),
+                None,
+                None
+            ),
+            Ok(fuzz_function_needed)
+        );
 
+        // Self types with paths are called through all of their segments
+        let impl_type: Type = syn::parse2(quote!(crate::foo::TestStruct<u8>)).unwrap();
         let fuzz_function_needed: ItemFn = syn::parse2(quote! {
-            pub fn __fuzz_maybe_checked_mul(mut input:__fuzz_struct_maybe_checked_mul) {
-                maybe_checked_mul(input.a, input.b, input.crash_on_overflow);
+            #[allow(non_snake_case)]
+            pub fn __fuzz_TestStruct_u8__new(mut input: __fuzz_struct_TestStruct_u8__new) {
+                <crate::foo::TestStruct<u8> >::new(input.a, input.b);
             }
         })
         .unwrap();
-        assert_eq!(fuzz_function(&function.sig, None), Ok(fuzz_function_needed));
+        assert_eq!(
+            fuzz_function(
+                &function.sig,
+                Some(&impl_type),
+                &TokenStream::new(),
+                None,
+                None
+            ),
+            Ok(fuzz_function_needed)
+        );
     }
 
     #[test]
-    fn function_borrowed() {
+    fn entry() {
         let function: ItemFn = syn::parse2(quote! {
-            pub fn maybe_checked_mul_borrowed(a: &mut u64, b: u64, crash_on_overflow: bool) {
+            pub fn maybe_checked_mul(a: u64, b: u64, crash_on_overflow: bool) -> u64 {
                 if crash_on_overflow {
-                    *a = a.checked_mul(b).expect("Overflow has occurred");
+                    a.checked_mul(b).expect("Overflow has occurred")
                 } else {
-                    *a = a.overflowing_mul(b).0;
+                    a.overflowing_mul(b).0
                 }
             }
         })
         .unwrap();
 
-        let fuzz_function_needed: ItemFn = syn::parse2(
-            quote! {
-                pub fn __fuzz_maybe_checked_mul_borrowed(mut input:__fuzz_struct_maybe_checked_mul_borrowed) {
-                    maybe_checked_mul_borrowed(&mut *input.a, input.b, input.crash_on_overflow);
+        let fuzz_entry_needed: ItemFn = syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn __auto_fuzz_lib__foo__bar__maybe_checked_mul(data: &[u8]) {
+                if let Ok(input) = <__fuzz_struct_foo__bar__maybe_checked_mul as ::arbitrary::Arbitrary>::arbitrary_take_rest(
+                    ::arbitrary::Unstructured::new(data),
+                ) {
+                    __fuzz_foo__bar__maybe_checked_mul(input);
                 }
             }
-        ).unwrap();
-        assert_eq!(fuzz_function(&function.sig, None), Ok(fuzz_function_needed));
+        })
+        .unwrap();
+        assert_eq!(
+            fuzz_entry(
+                &function.sig,
+                None,
+                &quote!(foo::bar),
+                &format_ident!("lib"),
+                &format_ident!("arbitrary"),
+                None
+            ),
+            Ok(fuzz_entry_needed)
+        );
     }
 
     #[test]
-    fn function_sliced_arg() {
+    fn raw() {
         let function: ItemFn = syn::parse2(quote! {
-            pub fn maybe_checked_mul(a: u64, b: u64, crash_on_overflow: bool, sl: &[u32]) -> u64 {
-                if crash_on_overflow {
-                    a.checked_mul(b).expect("Overflow has occurred")
-                } else {
-                    a.overflowing_mul(b).0
-                }
+            pub fn parse(data: &[u8]) -> Option<u8> {
+                data.first().copied()
             }
         })
         .unwrap();
+        assert!(is_raw(&function.sig));
+        for signature in [
+            quote!(fn parse(data: &mut [u8])),
+            quote!(fn parse(data: &[u16])),
+            quote!(fn parse(data: &[u8], len: usize)),
+            quote!(fn parse(&self)),
+        ] {
+            assert!(!is_raw(&syn::parse2(signature).unwrap()));
+        }
 
         let fuzz_function_needed: ItemFn = syn::parse2(quote! {
-            pub fn __fuzz_maybe_checked_mul(mut input:__fuzz_struct_maybe_checked_mul) {
-                maybe_checked_mul(input.a, input.b, input.crash_on_overflow, & *input.sl);
+            #[allow(non_snake_case)]
+            pub fn __fuzz_foo__Parser__parse(data: &[u8]) {
+                <Parser>::parse(data);
+            }
+        })
+        .unwrap();
+        let impl_type: Type = syn::parse2(quote!(Parser)).unwrap();
+        assert_eq!(
+            fuzz_function(&function.sig, Some(&impl_type), &quote!(foo), None, None),
+            Ok(fuzz_function_needed)
+        );
+
+        let fuzz_entry_needed: ItemFn = syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn __auto_fuzz_lib__parse(data: &[u8]) {
+                __fuzz_parse(data);
+            }
+        })
+        .unwrap();
+        let (module_path, crate_ident, arbitrary_crate) = (
+            TokenStream::new(),
+            format_ident!("lib"),
+            format_ident!("arbitrary"),
+        );
+        assert_eq!(
+            fuzz_entry(
+                &function.sig,
+                None,
+                &module_path,
+                &crate_ident,
+                &arbitrary_crate,
+                None
+            ),
+            Ok(fuzz_entry_needed)
+        );
+
+        let debug_entry_needed: ItemFn = syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn __auto_fuzz_debug__lib__parse(data: &[u8]) -> Option<String> {
+                Some(format!("{:?}", data))
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            fuzz_debug_entry(
+                &function.sig,
+                None,
+                &module_path,
+                &crate_ident,
+                &arbitrary_crate,
+                None
+            ),
+            Ok(debug_entry_needed)
+        );
+
+        let recorder_needed: Stmt = syn::parse2(quote! {
+            #[cfg(auto_fuzz_seeds)]
+            {
+                let mut encoder = ::auto_fuzz_test_runtime::seed::Encoder::new();
+                encoder.bytes(data);
+                ::auto_fuzz_test_runtime::seed::record("parse", "fuzz/corpus/parse", encoder);
             }
         })
         .unwrap();
-        assert_eq!(fuzz_function(&function.sig, None), Ok(fuzz_function_needed));
+        assert_tokens_eq!(
+            seed_recorder(&function.sig, None, &module_path, "fuzz/corpus/parse").unwrap(),
+            recorder_needed
+        );
+
+        let function: ItemFn = syn::parse2(quote!(
+            pub fn parse(#[fuzz(max_len = 4)] data: &[u8]) {}
+        ))
+        .unwrap();
+        assert!(matches!(
+            fuzz_function(&function.sig, None, &module_path, None, None),
+            Err(Error::Constraint(_))
+        ));
     }
 
     #[test]
-    fn function_complex_variable() {
+    fn debug_entry() {
         let function: ItemFn = syn::parse2(quote! {
-            pub fn maybe_checked_mul(a: u64, b: u64, (c,d):(u32,u64)) -> u64 {
+            pub fn maybe_checked_mul(a: u64, b: u64, crash_on_overflow: bool) -> u64 {
                 if crash_on_overflow {
                     a.checked_mul(b).expect("Overflow has occurred")
                 } else {
@@ -804,54 +2522,150 @@ mod tests {
             }
         })
         .unwrap();
+
+        let fuzz_debug_entry_needed: ItemFn = syn::parse2(quote! {
+            #[allow(non_snake_case)]
+            pub fn __auto_fuzz_debug__lib__foo__bar__maybe_checked_mul(data: &[u8]) -> Option<String> {
+                <__fuzz_struct_foo__bar__maybe_checked_mul as ::arbitrary::Arbitrary>::arbitrary_take_rest(
+                    ::arbitrary::Unstructured::new(data),
+                )
+                .ok()
+                .map(|input| format!("{:?}", input))
+            }
+        })
+        .unwrap();
         assert_eq!(
-            fuzz_function(&function.sig, None),
-            Err(Error::ComplexVariable)
+            fuzz_debug_entry(
+                &function.sig,
+                None,
+                &quote!(foo::bar),
+                &format_ident!("lib"),
+                &format_ident!("arbitrary"),
+                None
+            ),
+            Ok(fuzz_debug_entry_needed)
         );
     }
 
     #[test]
-    fn function_empty() {
-        let function: ItemFn = syn::parse2(quote! {
-            pub fn maybe_checked_mul_borrowed() {
-                if crash_on_overflow {
-                    *a = a.checked_mul(b).expect("Overflow has occurred");
-                } else {
-                    *a = a.overflowing_mul(b).0;
-                }
-            }
+    fn regression_generator_targets() {
+        let generator = regression_generator(
+            &format_ident!("lib"),
+            &["foo__bar".to_string(), "TestStruct__set_b".to_string()],
+        );
+        let file: syn::File = syn::parse2(generator).unwrap();
+
+        let targets_needed: syn::Item = syn::parse2(quote! {
+            const TARGETS: &[(&str, fn(&[u8]) -> Option<String>)] = &[
+                ("foo__bar", lib::__auto_fuzz::__auto_fuzz_debug__lib__foo__bar),
+                (
+                    "TestStruct__set_b",
+                    lib::__auto_fuzz::__auto_fuzz_debug__lib__TestStruct__set_b
+                ),
+            ];
         })
         .unwrap();
-        assert_eq!(fuzz_function(&function.sig, None), Err(Error::Empty));
+        assert!(file.items.contains(&targets_needed));
     }
 
     #[test]
-    fn method_unborrowed() {
-        let function: ItemFn = syn::parse2(quote! {
-            pub fn set_b(self, b: u64) -> u64 {
-                self.b + b
+    fn hub() {
+        let cfg: syn::Attribute = syn::parse_quote!(#[cfg(feature = "nested")]);
+        let entries = [
+            HubEntries {
+                cfgs: Vec::new(),
+                module_path: TokenStream::new(),
+                idents: vec![format_ident!("__auto_fuzz_lib__root")],
+            },
+            HubEntries {
+                cfgs: vec![cfg],
+                module_path: quote!(foo::bar),
+                idents: vec![
+                    format_ident!("__auto_fuzz_lib__foo__bar__baz"),
+                    format_ident!("__auto_fuzz_debug__lib__foo__bar__baz"),
+                ],
+            },
+        ];
+        let hub_needed = quote! {
+            #[doc(hidden)]
+            pub mod __auto_fuzz {
+                pub use crate::{__auto_fuzz_lib__root};
+                #[cfg(feature = "nested")]
+                pub use crate::foo::bar::{
+                    __auto_fuzz_lib__foo__bar__baz,
+                    __auto_fuzz_debug__lib__foo__bar__baz
+                };
             }
+        };
+        assert_tokens_eq!(entry_hub(&entries), hub_needed);
+    }
+
+    #[test]
+    fn corpus_replay_targets() {
+        let replay = corpus_replay(
+            &format_ident!("lib"),
+            &["foo__bar".to_string(), "TestStruct__set_b".to_string()],
+        );
+        let file: syn::File = syn::parse2(replay).unwrap();
+
+        let targets_needed: syn::Item = syn::parse2(quote! {
+            const TARGETS: &[(&str, fn(&[u8]), fn(&[u8]) -> Option<String>)] = &[
+                (
+                    "foo__bar",
+                    lib::__auto_fuzz::__auto_fuzz_lib__foo__bar,
+                    lib::__auto_fuzz::__auto_fuzz_debug__lib__foo__bar,
+                ),
+                (
+                    "TestStruct__set_b",
+                    lib::__auto_fuzz::__auto_fuzz_lib__TestStruct__set_b,
+                    lib::__auto_fuzz::__auto_fuzz_debug__lib__TestStruct__set_b,
+                ),
+            ];
         })
         .unwrap();
-        let implementation: ItemImpl = syn::parse2(quote! {
-            impl TestStruct {
+        assert!(file.items.contains(&targets_needed));
+    }
+
+    #[test]
+    fn seed_recorder_method() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn set_b(&mut self, b: u64) {
+                self.b = b;
             }
         })
         .unwrap();
-        let fuzz_function_needed: ItemFn = syn::parse2(quote! {
-            pub fn __fuzz_TestStruct_set_b(mut input: __fuzz_struct_TestStruct_set_b) {
-                    (input.slf).set_b(input.b);
+        let impl_type: Type = syn::parse2(quote!(TestStruct)).unwrap();
+        let recorder = seed_recorder(
+            &function.sig,
+            Some(&impl_type),
+            &TokenStream::new(),
+            "/crate/fuzz/corpus/TestStruct__set_b",
+        )
+        .unwrap();
+
+        let recorder_needed: Stmt = syn::parse2(quote! {
+            #[cfg(auto_fuzz_seeds)]
+            {
+                use ::auto_fuzz_test_runtime::seed::{EncodeSeed as _, NoSeed as _};
+                let mut encoder = ::auto_fuzz_test_runtime::seed::Encoder::new();
+                if true
+                    && (&::auto_fuzz_test_runtime::seed::Probe(&self)).encode_seed(&mut encoder)
+                    && (&::auto_fuzz_test_runtime::seed::Probe(&b)).encode_seed(&mut encoder)
+                {
+                    ::auto_fuzz_test_runtime::seed::record(
+                        "TestStruct__set_b",
+                        "/crate/fuzz/corpus/TestStruct__set_b",
+                        encoder
+                    );
+                }
             }
         })
         .unwrap();
-        assert_eq!(
-            fuzz_function(&function.sig, Some(&implementation.self_ty)),
-            Ok(fuzz_function_needed)
-        );
+        assert_tokens_eq!(recorder, recorder_needed);
     }
 
     #[test]
-    fn method_borrowed() {
+    fn property_test() {
         let function: ItemFn = syn::parse2(quote! {
             pub fn set_b(&mut self, b: u64) {
                 self.b = b;
@@ -863,45 +2677,120 @@ mod tests {
             }
         })
         .unwrap();
-        let fuzz_function_needed: ItemFn = syn::parse2(quote! {
-            pub fn __fuzz_TestStruct_set_b(mut input: __fuzz_struct_TestStruct_set_b) {
-                    (input.slf).set_b(input.b);
+
+        let fuzz_property_test_needed: ItemFn = syn::parse2(quote! {
+            #[test]
+            #[allow(non_snake_case)]
+            fn __fuzz_property_TestStruct__set_b() {
+                let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+                let mut next = move || {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state
+                };
+                let mut data = Vec::new();
+                for _ in 0..4096 {
+                    let len = (next() % 256) as usize;
+                    data.clear();
+                    data.extend((0..len).map(|_| next() as u8));
+                    if let Ok(input) = <__fuzz_struct_TestStruct__set_b as ::arb::Arbitrary>::arbitrary_take_rest(
+                        ::arb::Unstructured::new(&data),
+                    ) {
+                        __fuzz_TestStruct__set_b(input);
+                    }
+                }
             }
         })
         .unwrap();
         assert_eq!(
-            fuzz_function(&function.sig, Some(&implementation.self_ty)),
-            Ok(fuzz_function_needed)
+            fuzz_property_test(
+                &function.sig,
+                Some(&implementation.self_ty),
+                &TokenStream::new(),
+                &format_ident!("arb"),
+                None
+            ),
+            Ok(fuzz_property_test_needed)
         );
     }
 
     #[test]
-    fn method_generator() {
+    fn harness() {
         let function: ItemFn = syn::parse2(quote! {
-            pub fn new(a:u64, b:u64) -> TestStruct {
-                TestStruct {a,b}
+            pub fn maybe_checked_mul(a: u64, b: u64, crash_on_overflow: bool) -> u64 {
+                if crash_on_overflow {
+                    a.checked_mul(b).expect("Overflow has occurred")
+                } else {
+                    a.overflowing_mul(b).0
+                }
             }
         })
         .unwrap();
-        let implementation: ItemImpl = syn::parse2(quote! {
-            impl TestStruct {
+
+        let fuzz_harness_needed = quote! {
+            #![no_main]
+            use libfuzzer_sys::fuzz_target;
+            extern crate lib;
+
+            fuzz_target!(|data: &[u8]| lib::__auto_fuzz::__auto_fuzz_lib__foo__bar__maybe_checked_mul(data));
+        };
+
+        let attrs = quote!(foo::bar);
+        let crate_ident = format_ident!("lib");
+        assert_tokens_eq!(
+            fuzz_harness(
+                &function.sig,
+                None,
+                &crate_ident,
+                &attrs,
+                Backend::LibFuzzer
+            )
+            .unwrap(),
+            fuzz_harness_needed
+        );
+    }
+
+    #[test]
+    fn harness_afl() {
+        let function: ItemFn = syn::parse2(quote! {
+            pub fn set_b(&mut self, b: u64) {
+                self.b = b;
             }
         })
         .unwrap();
-        let fuzz_function_needed: ItemFn = syn::parse2(quote! {
-            pub fn __fuzz_TestStruct_new(mut input: __fuzz_struct_TestStruct_new) {
-                TestStruct::new(input.a, input.b);
+        let implementation: ItemImpl = syn::parse2(quote! {
+            impl TestStruct {
             }
         })
         .unwrap();
-        assert_eq!(
-            fuzz_function(&function.sig, Some(&implementation.self_ty)),
-            Ok(fuzz_function_needed)
+
+        let fuzz_harness_needed = quote! {
+            extern crate lib;
+
+            fn main() {
+                afl::fuzz!(|data: &[u8]| {
+                    lib::__auto_fuzz::__auto_fuzz_lib__TestStruct__set_b(data)
+                });
+            }
+        };
+
+        let crate_ident = format_ident!("lib");
+        assert_tokens_eq!(
+            fuzz_harness(
+                &function.sig,
+                Some(&implementation.self_ty),
+                &crate_ident,
+                &TokenStream::new(),
+                Backend::Afl
+            )
+            .unwrap(),
+            fuzz_harness_needed
         );
     }
 
     #[test]
-    fn harness() {
+    fn harness_honggfuzz() {
         let function: ItemFn = syn::parse2(quote! {
             pub fn maybe_checked_mul(a: u64, b: u64, crash_on_overflow: bool) -> u64 {
                 if crash_on_overflow {
@@ -914,20 +2803,29 @@ mod tests {
         .unwrap();
 
         let fuzz_harness_needed = quote! {
-            #![no_main]
-            use libfuzzer_sys::fuzz_target;
+            use honggfuzz::fuzz;
             extern crate lib;
 
-            fuzz_target!( |input: lib::foo::bar::__fuzz_struct_maybe_checked_mul| {
-                    lib::foo::bar::__fuzz_maybe_checked_mul(input);
+            fn main() {
+                loop {
+                    fuzz!(|data: &[u8]| {
+                        lib::__auto_fuzz::__auto_fuzz_lib__foo__bar__maybe_checked_mul(data)
+                    });
                 }
-            );
+            }
         };
 
         let attrs = quote!(foo::bar);
         let crate_ident = format_ident!("lib");
         assert_tokens_eq!(
-            fuzz_harness(&function.sig, None, &crate_ident, &attrs),
+            fuzz_harness(
+                &function.sig,
+                None,
+                &crate_ident,
+                &attrs,
+                Backend::Honggfuzz
+            )
+            .unwrap(),
             fuzz_harness_needed
         );
     }
@@ -0,0 +1,55 @@
+/// Matches `name` against a shell-like pattern, where `*` stands for any sequence of
+/// characters and `?` for a single character.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // Classic wildcard matching with backtracking to the last `*`
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal() {
+        assert!(matches("foo__bar", "foo__bar"));
+        assert!(!matches("foo__bar", "foo__baz"));
+        assert!(!matches("foo", "foo__bar"));
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(matches("*", "foo__bar"));
+        assert!(matches("*", ""));
+        assert!(matches("foo__*", "foo__bar__baz"));
+        assert!(matches("*__baz", "foo__bar__baz"));
+        assert!(matches("*bar*", "foo__bar__baz"));
+        assert!(matches("foo__ba?", "foo__bar"));
+        assert!(!matches("foo__ba?", "foo__ba"));
+        assert!(!matches("*__qux", "foo__bar__baz"));
+    }
+}
@@ -1,11 +1,19 @@
-use proc_macro2::TokenStream;
+use proc_macro2::{Span, TokenStream};
 use quote::{format_ident, quote};
 use std::env;
 use std::fs;
-use syn::{ImplItem, ItemFn, ItemImpl, ItemStruct, Type};
+use std::path::Path;
+use syn::{Block, FnArg, Ident, ImplItem, ItemFn, ItemImpl, ItemStruct, Signature, Type};
 
+mod attr;
 mod crate_parse;
+mod dictionary;
 mod generate;
+mod glob;
+#[allow(dead_code)]
+#[path = "../runtime/src/grammar.rs"]
+mod grammar;
+mod scan;
 
 #[proc_macro_attribute]
 pub fn create_cargofuzz_harness(
@@ -25,134 +33,791 @@ pub fn create_cargofuzz_impl_harness(
     proc_macro::TokenStream::from(output)
 }
 
-fn create_function_harness(attr: TokenStream, input: proc_macro::TokenStream) -> TokenStream {
-    let function: ItemFn = syn::parse(input).expect("Failed to parse input");
-
-    let fuzz_struct = generate::fuzz_struct(&function.sig, None).unwrap();
-    let fuzz_function = generate::fuzz_function(&function.sig, None).unwrap();
-
-    let crate_info = crate_parse::CrateInfo::from_root(
-        &env::current_dir().expect("Failed to obtain project root dir"),
-    )
-    .expect("Failed to obtain crate info");
-
-    let fuzz_dir_path = crate_info.fuzz_dir().expect("Failed to create fuzz dir");
-
-    let crate_name_underscored = str::replace(crate_info.crate_name(), "-", "_"); // required for `extern crate`
-
-    let crate_ident = format_ident!("{}", &crate_name_underscored);
-
-    // Writing fuzzing harness to file
-    let ident = crate_parse::construct_harness_ident(&function.sig.ident, None, &attr);
+#[proc_macro]
+pub fn create_cargofuzz_crate_harness(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let output = create_crate_harness(TokenStream::from(input));
+    proc_macro::TokenStream::from(output)
+}
 
-    let code = generate::fuzz_harness(&function.sig, None, &crate_ident, &attr);
+#[proc_macro]
+pub fn create_cargofuzz_entry_points(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let output = create_entry_points(TokenStream::from(input));
+    proc_macro::TokenStream::from(output)
+}
 
-    fs::write(
-        fuzz_dir_path.join(String::new() + &ident + ".rs"),
-        code.to_string(),
-    )
-    .expect("Failed to write fuzzing harness to fuzz/fuzz_targets");
-    // TODO: Error handing
+fn create_function_harness(attr: TokenStream, input: proc_macro::TokenStream) -> TokenStream {
+    let attr: attr::HarnessAttr = match syn::parse2(attr) {
+        Ok(attr) => attr,
+        Err(error) => return error.to_compile_error(),
+    };
+    let mut function: ItemFn = syn::parse(input).expect("Failed to parse input");
 
-    crate_info
-        .add_target_to_cargo_toml(&function.sig.ident, None, &attr)
-        .expect("Failed to update Cargo.toml");
+    let harness_items = function_harness_items(&attr, &mut function)
+        .unwrap_or_else(|error| error.to_compile_error());
+    attr::strip_arg_attrs(&mut function.sig);
 
     quote!(
         #function
-        #fuzz_struct
-        #fuzz_function
+        #harness_items
     )
 }
 
+fn function_harness_items(
+    attr: &attr::HarnessAttr,
+    function: &mut ItemFn,
+) -> syn::Result<TokenStream> {
+    attr::check_arg_attrs(&function.sig)?;
+    let context = Context::new(attr)?;
+    context.check_entry_points()?;
+    let mut harnesses = Harnesses::new(&context);
+    harnesses
+        .add(&function.sig, &function.block, None, &attr.module_path)
+        .map_err(|error| syn::Error::new_spanned(&function.sig.ident, error))?;
+    harnesses.record_seeds(&mut function.block, &function.sig, None, &attr.module_path);
+    Ok(harnesses.finish())
+}
+
 fn create_impl_harness(attr: TokenStream, input: proc_macro::TokenStream) -> TokenStream {
-    let implementation: ItemImpl = syn::parse(input).expect("Failed to parse input");
+    let attr: attr::HarnessAttr = match syn::parse2(attr) {
+        Ok(attr) => attr,
+        Err(error) => return error.to_compile_error(),
+    };
+    if let Some(precondition) = &attr.unsafe_precondition {
+        return syn::Error::new_spanned(
+            precondition,
+            "`unsafe_precondition` only applies to `#[create_cargofuzz_harness]`",
+        )
+        .to_compile_error();
+    }
+    let mut implementation: ItemImpl = syn::parse(input).expect("Failed to parse input");
     // Checking that the implementation meets the requirements
     assert_eq!(
         implementation.unsafety, None,
         "unsafe traits can not be fuzzed automatically."
     );
+
+    let harness_items = impl_harness_items(&attr, &mut implementation)
+        .unwrap_or_else(|error| error.to_compile_error());
+    for item in &mut implementation.items {
+        if let ImplItem::Method(method) = item {
+            attr::strip_arg_attrs(&mut method.sig);
+        }
+    }
+
+    quote!(
+        #implementation
+        #harness_items
+    )
+}
+
+fn impl_harness_items(
+    attr: &attr::HarnessAttr,
+    implementation: &mut ItemImpl,
+) -> syn::Result<TokenStream> {
+    crate_parse::impl_type_ident(&implementation.self_ty)?;
+    for item in &implementation.items {
+        if let ImplItem::Method(method) = item {
+            attr::check_arg_attrs(&method.sig)?;
+        }
+    }
     //assert!(
     //<Generic type parameter>,
     //"Generics are not currently supported."
     //);
     //TODO: tests
-    let crate_info = crate_parse::CrateInfo::from_root(
-        &env::current_dir().expect("Failed to obtain project root dir"),
+    let context = Context::new(attr)?;
+    context.check_entry_points()?;
+    let mut harnesses = Harnesses::new(&context);
+    let fuzzed = harnesses.add_impl(implementation, &attr.module_path);
+    let self_ty = implementation.self_ty.clone();
+    for item in &mut implementation.items {
+        if let ImplItem::Method(method) = item {
+            if fuzzed.contains(&method.sig.ident) {
+                harnesses.record_seeds(
+                    &mut method.block,
+                    &method.sig,
+                    Some(&self_ty),
+                    &attr.module_path,
+                );
+            }
+        }
+    }
+    Ok(harnesses.finish())
+}
+
+fn create_crate_harness(input: TokenStream) -> TokenStream {
+    let attr: attr::CrateHarnessAttr = match syn::parse2(input) {
+        Ok(attr) => attr,
+        Err(error) => return error.to_compile_error(),
+    };
+    let context = match Context::new(&attr.options) {
+        Ok(context) => context,
+        Err(error) => return error.to_compile_error(),
+    };
+
+    let modules = scan::scan_crate(
+        context.crate_info.crate_root(),
+        &attr.include,
+        &attr.exclude,
     )
-    .expect("Failed to obtain crate info");
+    .unwrap_or_else(|error| panic!("Failed to scan crate sources: {}", error));
 
-    let fuzz_dir_path = crate_info.fuzz_dir().expect("Failed to create fuzz dir");
+    // Generated items can not be put into the scanned modules, so every module gets a mirror,
+    // which sees the same names through imports.
+    let mut mirrors = Vec::new();
+    let mut hub_entries = Vec::new();
+    let mut targets = Vec::new();
+    for module in &modules {
+        let module_path = module.module_path();
+        let mut harnesses = Harnesses::new(&context);
+        for function in &module.functions {
+            if let Err(error) = harnesses.add(&function.sig, &function.block, None, &module_path) {
+                harnesses.skip(&function.sig.ident, error);
+            }
+        }
+        for implementation in &module.impls {
+            harnesses.add_impl(implementation, &module_path);
+        }
+        if harnesses.is_empty() {
+            continue;
+        }
 
-    let crate_name_underscored = str::replace(crate_info.crate_name(), "-", "_"); // required for `extern crate`
+        let mirror_ident = module.mirror_ident();
+        let glob_import = if module.path.is_empty() {
+            quote!(crate::*)
+        } else {
+            quote!(crate::#module_path::*)
+        };
+        let cfgs = &module.cfgs;
+        let uses = &module.uses;
+        hub_entries.push(generate::HubEntries {
+            cfgs: cfgs.clone(),
+            module_path: quote!(__auto_fuzz_crate::#mirror_ident),
+            idents: harnesses.entry_idents(),
+        });
+        targets.extend(harnesses.targets.iter().cloned());
+        let harness_items = harnesses.finish();
+        mirrors.push(quote!(
+            #(#cfgs)*
+            pub mod #mirror_ident {
+                use #glob_import;
+                #(#uses)*
+                #harness_items
+            }
+        ));
+    }
 
-    let crate_ident = format_ident!("{}", &crate_name_underscored);
+    let items_cfg = &context.items_cfg;
+    let entry_points = entry_points(&context, hub_entries, targets);
+    quote!(
+        #[cfg(#items_cfg)]
+        #[doc(hidden)]
+        #[allow(unused_imports)]
+        mod __auto_fuzz_crate {
+            #(#mirrors)*
+        }
+        #entry_points
+    )
+}
 
-    let mut fuzz_structs = Vec::<ItemStruct>::new();
-    let mut fuzz_functions = Vec::<ItemFn>::new();
+fn create_entry_points(input: TokenStream) -> TokenStream {
+    if !input.is_empty() {
+        return syn::Error::new_spanned(
+            input,
+            "`create_cargofuzz_entry_points` takes no arguments",
+        )
+        .to_compile_error();
+    }
+    match Context::new(&attr::HarnessAttr::default()) {
+        Ok(context) => entry_points(&context, Vec::new(), Vec::new()),
+        Err(error) => error.to_compile_error(),
+    }
+}
 
-    for item in &implementation.items {
-        if let ImplItem::Method(method) = item {
-            let fuzz_struct_result =
-                generate::fuzz_struct(&method.sig, Some(&implementation.self_ty));
-            let fuzz_function_result =
-                generate::fuzz_function(&method.sig, Some(&implementation.self_ty));
-
-            match (fuzz_struct_result, fuzz_function_result) {
-                (Ok(fuzz_struct), Ok(fuzz_function)) => {
-                    // Writing fuzzing harness to file
-                    let code = generate::fuzz_harness(
-                        &method.sig,
-                        Some(&implementation.self_ty),
-                        &crate_ident,
-                        &attr,
-                    );
-                    let filename = if let Type::Path(ref path) = *implementation.self_ty {
-                        format!(
-                            "{}_{}.rs",
-                            &(path.path.segments.iter().next().unwrap().ident).to_string(),
-                            &method.sig.ident.to_string()
-                        )
-                    } else {
-                        panic!("Complex self type.")
-                    };
-
-                    fs::write(fuzz_dir_path.join(filename), code.to_string())
-                        .expect("Failed to write fuzzing harness to fuzz/fuzz_targets");
-                    // TODO: Error handing
-
-                    crate_info
-                        .add_target_to_cargo_toml(
-                            &method.sig.ident,
+/// Re-exports entry points of items with harness attributes, along with `entries`, from the
+/// crate root, so the fuzzing crate can call them. The hub sees every harness of the crate, so it
+/// also drops targets, which are no longer generated, from the fuzzing crates. `targets` are
+/// harness idents of `entries`, which are written for the backend of `context`.
+fn entry_points(
+    context: &Context,
+    mut entries: Vec<generate::HubEntries>,
+    targets: Vec<String>,
+) -> TokenStream {
+    let harnessed = scan::scan_harnessed(context.crate_info.crate_root())
+        .unwrap_or_else(|error| panic!("Failed to scan crate sources: {}", error));
+
+    let mut errors = Vec::new();
+    let mut seen: Vec<Ident> = entries
+        .iter()
+        .flat_map(|entries| entries.idents.clone())
+        .collect();
+    let backend = context.crate_info.backend();
+    let mut targets: Vec<(crate_parse::Backend, String)> = targets
+        .into_iter()
+        .map(|target| (backend, target))
+        .collect();
+    for item in harnessed {
+        // Malformed attributes are reported by the attribute macros themselves
+        let attr: attr::HarnessAttr = match syn::parse2(attr_args(&item.attr)) {
+            Ok(attr) => attr,
+            Err(_) => continue,
+        };
+        let item_context = match Context::new(&attr) {
+            Ok(context) => context,
+            Err(_) => continue,
+        };
+        // Functions, which do not get a harness, do not get entry points either
+        let mut generated = Vec::new();
+        match &item.item {
+            syn::Item::Fn(function) => {
+                generated.extend(item_context.generate(&function.sig, None, &attr.module_path))
+            }
+            syn::Item::Impl(implementation)
+                if implementation.unsafety.is_none()
+                    && attr.unsafe_precondition.is_none()
+                    && crate_parse::impl_type_ident(&implementation.self_ty).is_ok() =>
+            {
+                for impl_item in &implementation.items {
+                    if let ImplItem::Method(method) = impl_item {
+                        generated.extend(item_context.generate(
+                            &method.sig,
                             Some(&implementation.self_ty),
-                            &attr,
-                        )
-                        .expect("Failed to update Cargo.toml");
-                    fuzz_structs.push(fuzz_struct);
-                    fuzz_functions.push(fuzz_function);
-                }
-                (Ok(_), Err(error)) => {
-                    eprintln!("Skipping method {}, due to:\n{}", &method.sig.ident, error);
-                    continue;
-                }
-                (Err(error), Ok(_)) => {
-                    eprintln!("Skipping method {}, due to\n{}", &method.sig.ident, error);
-                    continue;
-                }
-                (Err(_), Err(function_error)) => {
-                    eprintln!(
-                        "Skipping method {}, due to:\n{}",
-                        &method.sig.ident, function_error
-                    );
-                    continue;
+                            &attr.module_path,
+                        ));
+                    }
                 }
             }
+            _ => {}
+        }
+        // Clashing harness names are reported by the attribute macros
+        generated.retain(|generated| !seen.contains(&generated.fuzz_entry.sig.ident));
+        if generated.is_empty() {
+            continue;
+        }
+        let mut idents = Vec::new();
+        for generated in generated {
+            targets.push((item_context.crate_info.backend(), generated.ident));
+            idents.push(generated.fuzz_entry.sig.ident);
+            idents.push(generated.fuzz_debug_entry.sig.ident);
+        }
+        seen.extend(idents.iter().cloned());
+
+        let path = &item.path;
+        if let Some(hidden_by) = item.hidden_by {
+            let message = format!(
+                "Harnesses in `{}` can not be reached from the crate root, make module `{}` `pub(crate)`",
+                quote!(#(#path)::*).to_string().replace(' ', ""),
+                hidden_by
+            );
+            errors.push(syn::Error::new(Span::call_site(), message).to_compile_error());
+            continue;
+        }
+        entries.push(generate::HubEntries {
+            cfgs: item.cfgs,
+            module_path: quote!(#(#path)::*),
+            idents,
+        });
+    }
+
+    for backend in crate_parse::Backend::ALL {
+        let crate_info = context.crate_info.with_backend(backend);
+        let backend_targets: Vec<String> = targets
+            .iter()
+            .filter(|(target_backend, _)| *target_backend == backend)
+            .map(|(_, target)| target.clone())
+            .collect();
+        crate_info
+            .retain_fuzz_targets(&backend_targets)
+            .expect("Failed to remove stale fuzzing targets");
+        if crate_info.fuzz_crate_dir().join("Cargo.toml").is_file() {
+            write_generated_tests(&crate_info, &context.crate_ident);
         }
     }
 
+    let fuzz_cfg = &context.fuzz_cfg;
+    let hub = generate::entry_hub(&entries);
     quote!(
-        #implementation
-        #(#fuzz_structs)*
-        #(#fuzz_functions)*
+        #(#errors)*
+        #[cfg(#fuzz_cfg)]
+        #hub
     )
 }
+
+/// Arguments of an attribute, which may be written without parentheses.
+fn attr_args(attr: &syn::Attribute) -> TokenStream {
+    match attr.parse_args::<TokenStream>() {
+        Ok(args) => args,
+        Err(_) => TokenStream::new(),
+    }
+}
+
+/// Items generated for a fuzzed function, along with its harness and the harness name.
+struct Generated {
+    fuzz_struct: Option<(ItemStruct, ItemImpl)>,
+    fuzz_function: ItemFn,
+    fuzz_entry: ItemFn,
+    fuzz_debug_entry: ItemFn,
+    fuzz_property_test: Option<ItemFn>,
+    code: TokenStream,
+    ident: String,
+}
+
+/// Crate-wide settings, which are shared by all harnesses of a macro invocation.
+struct Context {
+    crate_info: crate_parse::CrateInfo,
+    crate_ident: Ident,
+    arbitrary_crate: Ident,
+    fuzz_cfg: TokenStream,
+    items_cfg: TokenStream,
+    property_tests: bool,
+    seeds: bool,
+    dictionary: crate_parse::DictionaryScope,
+    executor: Option<syn::Path>,
+    setup: Option<syn::Path>,
+    unsafe_precondition: Option<syn::Path>,
+}
+
+impl Context {
+    fn new(attr: &attr::HarnessAttr) -> syn::Result<Context> {
+        let crate_info = crate_parse::CrateInfo::from_root(
+            &env::current_dir().expect("Failed to obtain project root dir"),
+        )
+        .map_err(|error| syn::Error::new(Span::call_site(), error))?;
+        let crate_info =
+            crate_info.with_backend(attr.backend.unwrap_or_else(|| crate_info.backend()));
+
+        let crate_name_underscored = str::replace(crate_info.crate_name(), "-", "_"); // required for `extern crate`
+        let crate_ident = format_ident!("{}", &crate_name_underscored);
+        let arbitrary_crate = format_ident!("{}", crate_info.arbitrary_crate());
+
+        // Generated items require `arbitrary`, so they must not leak into regular builds.
+        let fuzz_cfg: TokenStream = crate_info
+            .fuzz_cfg()
+            .parse()
+            .expect("Failed to parse cfg predicate for fuzzing code");
+        let property_tests = attr
+            .property_tests
+            .unwrap_or_else(|| crate_info.property_tests());
+        // Property tests drive the same fuzz structs and functions from `cargo test`.
+        let items_cfg = if property_tests {
+            quote!(any(#fuzz_cfg, test))
+        } else {
+            fuzz_cfg.clone()
+        };
+
+        let seeds = attr.seeds.unwrap_or_else(|| crate_info.seeds());
+        let dictionary = attr.dictionary.unwrap_or_else(|| crate_info.dictionary());
+        let executor = match (&attr.executor, crate_info.executor()) {
+            (Some(executor), _) => Some(executor.clone()),
+            (None, Some(executor)) => Some(
+                syn::parse_str(executor)
+                    .map_err(|error| syn::Error::new(Span::call_site(), error))?,
+            ),
+            (None, None) => None,
+        };
+
+        let setup = match (&attr.setup, crate_info.setup()) {
+            (Some(setup), _) => Some(setup.clone()),
+            (None, Some(setup)) => Some(
+                syn::parse_str(setup).map_err(|error| syn::Error::new(Span::call_site(), error))?,
+            ),
+            (None, None) => None,
+        };
+
+        Ok(Context {
+            crate_info,
+            crate_ident,
+            arbitrary_crate,
+            fuzz_cfg,
+            items_cfg,
+            property_tests,
+            seeds,
+            dictionary,
+            executor,
+            setup,
+            unsafe_precondition: attr.unsafe_precondition.clone(),
+        })
+    }
+
+    /// Fails, unless the crate root has the `__auto_fuzz` module, through which the fuzzing crate
+    /// calls entry points of harness attributes.
+    fn check_entry_points(&self) -> syn::Result<()> {
+        match scan::has_entry_points(self.crate_info.crate_root()) {
+            Ok(false) => Err(syn::Error::new(
+                Span::call_site(),
+                "Harnesses call entry points through the crate root, add \
+                 `create_cargofuzz_entry_points!();` to `src/lib.rs`",
+            )),
+            // Sources, which can not be scanned, are reported by the crate root
+            Ok(true) | Err(_) => Ok(()),
+        }
+    }
+
+    /// Generates everything for a fuzzed function without touching the fuzzing crate.
+    fn generate(
+        &self,
+        signature: &Signature,
+        impl_type: Option<&Type>,
+        module_path: &TokenStream,
+    ) -> Result<Generated, generate::Error> {
+        // Raw byte slice functions take the fuzzer's input without a fuzz struct
+        let fuzz_struct = if generate::is_raw(signature) {
+            None
+        } else {
+            let fuzz_struct = generate::fuzz_struct(signature, impl_type, module_path)?;
+            let fuzz_arbitrary_impl =
+                generate::fuzz_arbitrary_impl(&fuzz_struct, signature, &self.arbitrary_crate)?;
+            Some((fuzz_struct, fuzz_arbitrary_impl))
+        };
+        let fuzz_function = generate::fuzz_function(
+            signature,
+            impl_type,
+            module_path,
+            self.executor.as_ref(),
+            self.unsafe_precondition.as_ref(),
+        )?;
+        let fuzz_entry = generate::fuzz_entry(
+            signature,
+            impl_type,
+            module_path,
+            &self.crate_ident,
+            &self.arbitrary_crate,
+            self.setup.as_ref(),
+        )?;
+        let fuzz_debug_entry = generate::fuzz_debug_entry(
+            signature,
+            impl_type,
+            module_path,
+            &self.crate_ident,
+            &self.arbitrary_crate,
+            self.setup.as_ref(),
+        )?;
+        let fuzz_property_test = if self.property_tests {
+            let mut property_test = generate::fuzz_property_test(
+                signature,
+                impl_type,
+                module_path,
+                &self.arbitrary_crate,
+                self.setup.as_ref(),
+            )?;
+            if self.seeds {
+                property_test.block.stmts.insert(0, generate::seed_pause());
+            }
+            Some(property_test)
+        } else {
+            None
+        };
+
+        let code = generate::fuzz_harness(
+            signature,
+            impl_type,
+            &self.crate_ident,
+            module_path,
+            self.crate_info.backend(),
+        )?;
+        let ident = crate_parse::construct_harness_ident(&signature.ident, impl_type, module_path)
+            .map_err(|_| generate::Error::ComplexSelfType)?;
+        Ok(Generated {
+            fuzz_struct,
+            fuzz_function,
+            fuzz_entry,
+            fuzz_debug_entry,
+            fuzz_property_test,
+            code,
+            ident,
+        })
+    }
+}
+
+/// Items generated for fuzzed functions, whose harnesses are already written to the fuzzing crate.
+struct Harnesses<'a> {
+    context: &'a Context,
+    fuzz_structs: Vec<ItemStruct>,
+    fuzz_arbitrary_impls: Vec<ItemImpl>,
+    fuzz_functions: Vec<ItemFn>,
+    fuzz_entries: Vec<ItemFn>,
+    fuzz_debug_entries: Vec<ItemFn>,
+    fuzz_property_tests: Vec<ItemFn>,
+    /// Literals of the module, which are shared by dictionaries of all its targets.
+    module_literals: Option<dictionary::Literals>,
+    /// Clashes of harness names, which can not be skipped like unsupported functions.
+    errors: Vec<syn::Error>,
+    /// Harness idents, which are also names of targets in the fuzzing crate.
+    targets: Vec<String>,
+}
+
+impl<'a> Harnesses<'a> {
+    fn new(context: &'a Context) -> Harnesses<'a> {
+        Harnesses {
+            context,
+            fuzz_structs: Vec::new(),
+            fuzz_arbitrary_impls: Vec::new(),
+            fuzz_functions: Vec::new(),
+            fuzz_entries: Vec::new(),
+            fuzz_debug_entries: Vec::new(),
+            fuzz_property_tests: Vec::new(),
+            module_literals: None,
+            errors: Vec::new(),
+            targets: Vec::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.fuzz_entries.is_empty() && self.errors.is_empty()
+    }
+
+    fn add(
+        &mut self,
+        signature: &Signature,
+        block: &Block,
+        impl_type: Option<&Type>,
+        module_path: &TokenStream,
+    ) -> Result<(), generate::Error> {
+        let context = self.context;
+        let Generated {
+            fuzz_struct,
+            fuzz_function,
+            fuzz_entry,
+            fuzz_debug_entry,
+            fuzz_property_test,
+            code,
+            ident,
+        } = context.generate(signature, impl_type, module_path)?;
+        crate_parse::claim_harness_ident(
+            context.crate_info.crate_name(),
+            &ident,
+            &crate_parse::function_path(&signature.ident, impl_type, module_path),
+        )
+        .map_err(generate::Error::DuplicateName)?;
+
+        // Writing fuzzing harness to file
+        let fuzz_dir_path = context
+            .crate_info
+            .fuzz_dir()
+            .expect("Failed to create fuzz dir");
+        fs::write(
+            fuzz_dir_path.join(format!("{}.rs", ident)),
+            code.to_string(),
+        )
+        .expect("Failed to write fuzzing harness to fuzz/fuzz_targets");
+        // TODO: Error handing
+        self.write_dictionary(&ident, block, module_path, &fuzz_dir_path);
+
+        context
+            .crate_info
+            .add_target_to_cargo_toml(&signature.ident, impl_type, module_path)
+            .expect("Failed to update Cargo.toml");
+
+        if let Some((fuzz_struct, fuzz_arbitrary_impl)) = fuzz_struct {
+            self.fuzz_structs.push(fuzz_struct);
+            self.fuzz_arbitrary_impls.push(fuzz_arbitrary_impl);
+        }
+        self.fuzz_functions.push(fuzz_function);
+        self.fuzz_entries.push(fuzz_entry);
+        self.fuzz_debug_entries.push(fuzz_debug_entry);
+        self.fuzz_property_tests.extend(fuzz_property_test);
+        self.targets.push(ident);
+        Ok(())
+    }
+
+    /// Idents of entry points, which `generate::entry_hub` re-exports.
+    fn entry_idents(&self) -> Vec<Ident> {
+        self.fuzz_entries
+            .iter()
+            .chain(&self.fuzz_debug_entries)
+            .map(|entry| entry.sig.ident.clone())
+            .collect()
+    }
+
+    /// Returns idents of methods, which got a harness.
+    fn add_impl(&mut self, implementation: &ItemImpl, module_path: &TokenStream) -> Vec<Ident> {
+        let mut fuzzed = Vec::new();
+        for item in &implementation.items {
+            if let ImplItem::Method(method) = item {
+                match self.add(
+                    &method.sig,
+                    &method.block,
+                    Some(&implementation.self_ty),
+                    module_path,
+                ) {
+                    Ok(()) => fuzzed.push(method.sig.ident.clone()),
+                    Err(error) => self.skip(&method.sig.ident, error),
+                }
+            }
+        }
+        fuzzed
+    }
+
+    /// Reports a function, which did not get a harness. Clashing names fail the build, as the
+    /// user would not notice the missing harness otherwise.
+    fn skip(&mut self, function: &Ident, error: generate::Error) {
+        match error {
+            generate::Error::DuplicateName(_) => {
+                self.errors.push(syn::Error::new_spanned(function, error))
+            }
+            error => eprintln!("Skipping {}, due to:\n{}", function, error),
+        }
+    }
+
+    /// Writes literals of the fuzzed code into a dictionary next to the harness.
+    fn write_dictionary(
+        &mut self,
+        ident: &str,
+        block: &Block,
+        module_path: &TokenStream,
+        fuzz_dir_path: &Path,
+    ) {
+        let mut literals = dictionary::Literals::default();
+        match self.context.dictionary {
+            crate_parse::DictionaryScope::None => {}
+            crate_parse::DictionaryScope::Function => literals.add_block(block),
+            crate_parse::DictionaryScope::Module => {
+                literals.add_block(block);
+                literals.extend(self.module_literals(module_path));
+            }
+        }
+
+        let dictionary_path = fuzz_dir_path.join(format!("{}.dict", ident));
+        if literals.is_empty() {
+            // A stale dictionary would outlive literals removed from the code
+            let _ = fs::remove_file(&dictionary_path);
+        } else {
+            fs::write(&dictionary_path, literals.render(ident))
+                .expect("Failed to write fuzzing dictionary");
+        }
+    }
+
+    fn module_literals(&mut self, module_path: &TokenStream) -> &dictionary::Literals {
+        let crate_root = self.context.crate_info.crate_root();
+        self.module_literals.get_or_insert_with(|| {
+            let path: Vec<Ident> = if module_path.is_empty() {
+                Vec::new()
+            } else {
+                syn::parse2::<syn::Path>(module_path.clone())
+                    .expect("Failed to parse module path")
+                    .segments
+                    .into_iter()
+                    .map(|segment| segment.ident)
+                    .filter(|ident| ident != "crate")
+                    .collect()
+            };
+            let mut literals = dictionary::Literals::default();
+            match scan::module_items(crate_root, &path) {
+                Ok(items) => literals.add_items(&items),
+                Err(error) => eprintln!(
+                    "Dictionary is limited to the fuzzed function, due to:\n{}",
+                    error
+                ),
+            }
+            literals
+        })
+    }
+
+    /// Makes a fuzzed function record its arguments as seeds, when the crate is built with
+    /// `--cfg auto_fuzz_seeds`.
+    fn record_seeds(
+        &self,
+        block: &mut Block,
+        signature: &Signature,
+        impl_type: Option<&Type>,
+        module_path: &TokenStream,
+    ) {
+        // Recorded arguments would not round trip through ranges and custom generators, nor
+        // through bytes, which the setup hook consumes
+        let keeps_encoding = signature.inputs.iter().all(|input| match input {
+            FnArg::Typed(arg) => match attr::ArgConstraint::from_attrs(&arg.attrs) {
+                Ok(Some(constraint)) => constraint.keeps_encoding(),
+                _ => true,
+            },
+            FnArg::Receiver(_) => true,
+        });
+        if !self.context.seeds || !keeps_encoding || self.context.setup.is_some() {
+            return;
+        }
+        let ident = crate_parse::construct_harness_ident(&signature.ident, impl_type, module_path)
+            .expect("Fuzzed functions have valid harness idents");
+        let seed_dir = self.context.crate_info.seed_dir(&ident);
+        let recorder = generate::seed_recorder(
+            signature,
+            impl_type,
+            module_path,
+            seed_dir.to_str().expect("Seed dir path is not valid UTF-8"),
+        )
+        .expect("Failed to generate seed recorder");
+        block.stmts.insert(0, recorder);
+    }
+
+    fn finish(self) -> TokenStream {
+        let context = self.context;
+        if !self.is_empty() {
+            write_generated_tests(&context.crate_info, &context.crate_ident);
+        }
+
+        let fuzz_cfg = &context.fuzz_cfg;
+        let items_cfg = &context.items_cfg;
+        let Harnesses {
+            fuzz_structs,
+            fuzz_arbitrary_impls,
+            fuzz_functions,
+            fuzz_entries,
+            fuzz_debug_entries,
+            fuzz_property_tests,
+            errors,
+            ..
+        } = self;
+        let errors = errors.iter().map(syn::Error::to_compile_error);
+        quote!(
+            #(#errors)*
+            #(
+                #[cfg(#items_cfg)]
+                #[doc(hidden)]
+                #fuzz_structs
+            )*
+            #(
+                #[cfg(#items_cfg)]
+                #fuzz_arbitrary_impls
+            )*
+            #(
+                #[cfg(#items_cfg)]
+                #[doc(hidden)]
+                #fuzz_functions
+            )*
+            #(
+                #[cfg(#fuzz_cfg)]
+                #[doc(hidden)]
+                #fuzz_entries
+            )*
+            #(
+                #[cfg(#fuzz_cfg)]
+                #[doc(hidden)]
+                #fuzz_debug_entries
+            )*
+            #(
+                #[cfg(test)]
+                #fuzz_property_tests
+            )*
+        )
+    }
+}
+
+fn write_generated_tests(crate_info: &crate_parse::CrateInfo, crate_ident: &Ident) {
+    // Only cargo-fuzz keeps corpora and crash artifacts in a known place.
+    if crate_info.backend() != crate_parse::Backend::LibFuzzer {
+        return;
+    }
+    let targets = crate_info
+        .fuzz_targets()
+        .expect("Failed to read fuzzing targets");
+    let tests_dir_path = crate_info.tests_dir().expect("Failed to create fuzz/tests");
+
+    let code = generate::regression_generator(crate_ident, &targets);
+    fs::write(
+        tests_dir_path.join("generate_regressions.rs"),
+        code.to_string(),
+    )
+    .expect("Failed to write regression tests generator to fuzz/tests");
+
+    let code = generate::corpus_replay(crate_ident, &targets);
+    fs::write(tests_dir_path.join("replay_corpus.rs"), code.to_string())
+        .expect("Failed to write corpus replay to fuzz/tests");
+}
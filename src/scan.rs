@@ -0,0 +1,756 @@
+use crate::glob;
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use syn::{
+    Attribute, Ident, ImplItem, Item, ItemFn, ItemImpl, ItemMod, ItemUse, UseGroup, UsePath,
+    UseTree, Visibility,
+};
+
+/// Module of the user's crate along with its items, which are worth fuzzing.
+pub struct ScannedModule {
+    pub path: Vec<Ident>,
+    /// `cfg` attributes of the module and all of its parents.
+    pub cfgs: Vec<Attribute>,
+    /// Imports of the module, rewritten to be valid anywhere in the crate.
+    pub uses: Vec<ItemUse>,
+    pub functions: Vec<ItemFn>,
+    pub impls: Vec<ItemImpl>,
+}
+
+impl ScannedModule {
+    pub fn module_path(&self) -> TokenStream {
+        let path = &self.path;
+        quote!(#(#path)::*)
+    }
+
+    /// Name of the module, which holds generated items for this one.
+    pub fn mirror_ident(&self) -> Ident {
+        let suffix: String = self
+            .path
+            .iter()
+            .map(|segment| format!("__{}", segment))
+            .collect();
+        format_ident!("__mod{}", suffix)
+    }
+}
+
+/// Walks the crate from `src/lib.rs` and collects public free functions and inherent methods,
+/// whose paths, or paths of their modules, pass `include` and `exclude` glob patterns.
+pub fn scan_crate(
+    crate_root: &Path,
+    include: &[String],
+    exclude: &[String],
+) -> Result<Vec<ScannedModule>, String> {
+    let mut scanner = Scanner {
+        include,
+        exclude,
+        local_items: HashMap::new(),
+        modules: Vec::new(),
+    };
+    let src_dir = crate_root.join("src");
+    let file = parse_file(&src_dir.join("lib.rs"))?;
+    scanner.scan_module(file.items, Vec::new(), Vec::new(), src_dir)?;
+    Ok(scanner.modules)
+}
+
+/// Item with a harness attribute, along with the module it is defined in.
+pub struct HarnessedItem {
+    pub path: Vec<Ident>,
+    /// `cfg` attributes of the item and all of its modules.
+    pub cfgs: Vec<Attribute>,
+    /// Module on the path, which is private to its parent, so the crate root can not reach
+    /// entry points of the item.
+    pub hidden_by: Option<Ident>,
+    pub attr: Attribute,
+    pub item: Item,
+}
+
+/// Walks the crate from `src/lib.rs` and collects functions and implementations with harness
+/// attributes, except for test-only ones.
+pub fn scan_harnessed(crate_root: &Path) -> Result<Vec<HarnessedItem>, String> {
+    let src_dir = crate_root.join("src");
+    let file = parse_file(&src_dir.join("lib.rs"))?;
+    let mut harnessed = Vec::new();
+    collect_harnessed(
+        file.items,
+        Vec::new(),
+        Vec::new(),
+        None,
+        &src_dir,
+        &mut harnessed,
+    )?;
+    Ok(harnessed)
+}
+
+/// Whether the crate root invokes one of the macros, which generate the `__auto_fuzz` module.
+pub fn has_entry_points(crate_root: &Path) -> Result<bool, String> {
+    let file = parse_file(&crate_root.join("src").join("lib.rs"))?;
+    Ok(file.items.iter().any(|item| match item {
+        Item::Macro(item_macro) => item_macro
+            .mac
+            .path
+            .segments
+            .last()
+            .map(|segment| {
+                segment.ident == "create_cargofuzz_entry_points"
+                    || segment.ident == "create_cargofuzz_crate_harness"
+            })
+            .unwrap_or(false),
+        _ => false,
+    }))
+}
+
+fn collect_harnessed(
+    items: Vec<Item>,
+    path: Vec<Ident>,
+    cfgs: Vec<Attribute>,
+    hidden_by: Option<Ident>,
+    dir: &Path,
+    harnessed: &mut Vec<HarnessedItem>,
+) -> Result<(), String> {
+    for item in items {
+        let (attrs, attr_name) = match &item {
+            Item::Fn(function) => (&function.attrs, "create_cargofuzz_harness"),
+            Item::Impl(implementation) => (&implementation.attrs, "create_cargofuzz_impl_harness"),
+            Item::Mod(item_mod) => {
+                if is_test_only(&item_mod.attrs) {
+                    continue;
+                }
+                let mut child_cfgs = cfgs.clone();
+                child_cfgs.extend(cfg_attrs(&item_mod.attrs));
+                let child_hidden_by = hidden_by.clone().or_else(|| {
+                    if path.is_empty() || is_visible_in_crate(&item_mod.vis) {
+                        None
+                    } else {
+                        Some(item_mod.ident.clone())
+                    }
+                });
+                let child_items = match &item_mod.content {
+                    Some((_, items)) => items.clone(),
+                    None => parse_file(&module_file(dir, item_mod)?)?.items,
+                };
+                let mut child_path = path.clone();
+                child_path.push(item_mod.ident.clone());
+                collect_harnessed(
+                    child_items,
+                    child_path,
+                    child_cfgs,
+                    child_hidden_by,
+                    &dir.join(item_mod.ident.to_string()),
+                    harnessed,
+                )?;
+                continue;
+            }
+            _ => continue,
+        };
+        if is_test_only(attrs) {
+            continue;
+        }
+        let attr = attrs.iter().find(|attr| {
+            attr.path
+                .segments
+                .last()
+                .map(|segment| segment.ident == attr_name)
+                .unwrap_or(false)
+        });
+        if let Some(attr) = attr {
+            let mut item_cfgs = cfgs.clone();
+            item_cfgs.extend(cfg_attrs(attrs));
+            harnessed.push(HarnessedItem {
+                path: path.clone(),
+                cfgs: item_cfgs,
+                hidden_by: hidden_by.clone(),
+                attr: attr.clone(),
+                item: item.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Items of the module at `path`, following module declarations from `src/lib.rs`.
+pub fn module_items(crate_root: &Path, path: &[Ident]) -> Result<Vec<Item>, String> {
+    let mut dir = crate_root.join("src");
+    let mut items = parse_file(&dir.join("lib.rs"))?.items;
+    for segment in path {
+        let item_mod = items
+            .into_iter()
+            .find_map(|item| match item {
+                Item::Mod(item_mod) if item_mod.ident == *segment => Some(item_mod),
+                _ => None,
+            })
+            .ok_or_else(|| format!("Failed to find module `{}`", segment))?;
+        items = match item_mod.content {
+            Some((_, items)) => items,
+            None => parse_file(&module_file(&dir, &item_mod)?)?.items,
+        };
+        dir = dir.join(segment.to_string());
+    }
+    Ok(items)
+}
+
+/// Names of items of every module, along with their visibility outside of the module.
+type LocalItems = HashMap<Vec<String>, Vec<(Ident, bool)>>;
+
+struct Scanner<'a> {
+    include: &'a [String],
+    exclude: &'a [String],
+    local_items: LocalItems,
+    modules: Vec<ScannedModule>,
+}
+
+impl Scanner<'_> {
+    fn scan_module(
+        &mut self,
+        items: Vec<Item>,
+        path: Vec<Ident>,
+        cfgs: Vec<Attribute>,
+        dir: PathBuf,
+    ) -> Result<(), String> {
+        // Private items of the crate root are visible everywhere, unlike ones of other modules
+        let local_items = items
+            .iter()
+            .filter_map(|item| {
+                item_ident_and_visibility(item)
+                    .map(|(ident, vis)| (ident, path.is_empty() || is_visible_in_crate(vis)))
+            })
+            .collect();
+        self.local_items.insert(path_key(&path), local_items);
+
+        let mut module = ScannedModule {
+            path: path.clone(),
+            cfgs: cfgs.clone(),
+            uses: Vec::new(),
+            functions: Vec::new(),
+            impls: Vec::new(),
+        };
+        for item in items {
+            match item {
+                Item::Mod(item_mod) => {
+                    if is_test_only(&item_mod.attrs)
+                        || !(path.is_empty() || is_visible_in_crate(&item_mod.vis))
+                    {
+                        continue;
+                    }
+                    let mut child_cfgs = cfgs.clone();
+                    child_cfgs.extend(cfg_attrs(&item_mod.attrs));
+                    let child_dir = dir.join(item_mod.ident.to_string());
+                    let child_items = match item_mod.content {
+                        Some((_, ref items)) => items.clone(),
+                        None => parse_file(&module_file(&dir, &item_mod)?)?.items,
+                    };
+                    let mut child_path = path.clone();
+                    child_path.push(item_mod.ident);
+                    self.scan_module(child_items, child_path, child_cfgs, child_dir)?;
+                }
+                Item::Use(item_use) => {
+                    if has_cfg(&item_use.attrs) || item_use.leading_colon.is_some() {
+                        if !is_test_only(&item_use.attrs) {
+                            module.uses.push(item_use);
+                        }
+                        continue;
+                    }
+                    if let Some(tree) =
+                        absolute_use_tree(item_use.tree.clone(), &path, &self.local_items)
+                    {
+                        module.uses.push(ItemUse {
+                            vis: Visibility::Inherited,
+                            tree,
+                            ..item_use
+                        });
+                    }
+                }
+                Item::Fn(function) => {
+                    if let Visibility::Public(_) = function.vis {
+                        if function.sig.generics.params.is_empty()
+                            && !is_skipped(&function.attrs)
+                            && self.is_included(&path, &[&function.sig.ident])
+                        {
+                            module.functions.push(function);
+                        }
+                    }
+                }
+                Item::Impl(implementation) => {
+                    if let Some(mut implementation) = fuzzable_impl(implementation) {
+                        if let Some(type_ident) = impl_type_ident(&implementation) {
+                            implementation.items.retain(|item| match item {
+                                ImplItem::Method(method) => {
+                                    self.is_included(&path, &[&type_ident, &method.sig.ident])
+                                }
+                                _ => false,
+                            });
+                        }
+                        if !implementation.items.is_empty() {
+                            module.impls.push(implementation);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if !module.functions.is_empty() || !module.impls.is_empty() {
+            self.modules.push(module);
+        }
+        Ok(())
+    }
+
+    /// Whether patterns let through the item, like `Config::new`, in the module `path`. Patterns
+    /// match either the path of the module, or the full path of the item.
+    fn is_included(&self, path: &[Ident], item: &[&Ident]) -> bool {
+        let module_path = path
+            .iter()
+            .map(Ident::to_string)
+            .collect::<Vec<_>>()
+            .join("::");
+        let item_path = path
+            .iter()
+            .chain(item.iter().copied())
+            .map(Ident::to_string)
+            .collect::<Vec<_>>()
+            .join("::");
+        let matches = |patterns: &[String]| {
+            patterns.iter().any(|pattern| {
+                glob::matches(pattern, &module_path) || glob::matches(pattern, &item_path)
+            })
+        };
+        (self.include.is_empty() || matches(self.include)) && !matches(self.exclude)
+    }
+}
+
+fn parse_file(path: &Path) -> Result<syn::File, String> {
+    let source = fs::read_to_string(path)
+        .map_err(|error| format!("Failed to read {}: {}", path.display(), error))?;
+    syn::parse_file(&source)
+        .map_err(|error| format!("Failed to parse {}: {}", path.display(), error))
+}
+
+fn module_file(dir: &Path, item_mod: &ItemMod) -> Result<PathBuf, String> {
+    for attr in &item_mod.attrs {
+        if attr.path.is_ident("path") {
+            if let Ok(syn::Meta::NameValue(syn::MetaNameValue {
+                lit: syn::Lit::Str(path),
+                ..
+            })) = attr.parse_meta()
+            {
+                return Ok(dir.join(path.value()));
+            }
+        }
+    }
+    let name = item_mod.ident.to_string();
+    let candidates = [
+        dir.join(format!("{}.rs", name)),
+        dir.join(&name).join("mod.rs"),
+    ];
+    candidates
+        .iter()
+        .find(|candidate| candidate.is_file())
+        .cloned()
+        .ok_or_else(|| format!("Failed to find source file of module `{}`", name))
+}
+
+fn fuzzable_impl(mut implementation: ItemImpl) -> Option<ItemImpl> {
+    if implementation.trait_.is_some()
+        || implementation.unsafety.is_some()
+        || !implementation.generics.params.is_empty()
+        || is_skipped(&implementation.attrs)
+    {
+        return None;
+    }
+    implementation.items.retain(|item| match item {
+        ImplItem::Method(method) => {
+            matches!(method.vis, Visibility::Public(_))
+                && method.sig.generics.params.is_empty()
+                && !is_skipped(&method.attrs)
+        }
+        _ => false,
+    });
+    if implementation.items.is_empty() {
+        None
+    } else {
+        Some(implementation)
+    }
+}
+
+/// Name of the self type, which method paths are matched with.
+fn impl_type_ident(implementation: &ItemImpl) -> Option<Ident> {
+    match &*implementation.self_ty {
+        syn::Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.clone()),
+        _ => None,
+    }
+}
+
+fn is_skipped(attrs: &[Attribute]) -> bool {
+    // Items, which already have a harness, or exist only in some configurations
+    has_cfg(attrs)
+        || attrs.iter().any(|attr| {
+            attr.path.is_ident("test")
+                || attr
+                    .path
+                    .segments
+                    .last()
+                    .map(|segment| segment.ident.to_string().starts_with("create_cargofuzz"))
+                    .unwrap_or(false)
+        })
+}
+
+fn cfg_attrs(attrs: &[Attribute]) -> impl Iterator<Item = Attribute> + '_ {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("cfg"))
+        .cloned()
+}
+
+fn has_cfg(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path.is_ident("cfg"))
+}
+
+pub fn is_test_only(attrs: &[Attribute]) -> bool {
+    attrs
+        .iter()
+        .any(|attr| attr.path.is_ident("cfg") && attr.tokens.to_string() == "(test)")
+}
+
+fn is_visible_in_crate(vis: &Visibility) -> bool {
+    match vis {
+        Visibility::Public(_) | Visibility::Crate(_) => true,
+        Visibility::Restricted(restricted) => {
+            restricted.in_token.is_none() && restricted.path.is_ident("crate")
+        }
+        Visibility::Inherited => false,
+    }
+}
+
+fn item_ident_and_visibility(item: &Item) -> Option<(Ident, &Visibility)> {
+    match item {
+        Item::Const(item) => Some((item.ident.clone(), &item.vis)),
+        Item::Enum(item) => Some((item.ident.clone(), &item.vis)),
+        Item::Fn(item) => Some((item.sig.ident.clone(), &item.vis)),
+        Item::Mod(item) => Some((item.ident.clone(), &item.vis)),
+        Item::Static(item) => Some((item.ident.clone(), &item.vis)),
+        Item::Struct(item) => Some((item.ident.clone(), &item.vis)),
+        Item::Trait(item) => Some((item.ident.clone(), &item.vis)),
+        Item::Type(item) => Some((item.ident.clone(), &item.vis)),
+        Item::Union(item) => Some((item.ident.clone(), &item.vis)),
+        _ => None,
+    }
+}
+
+/// Rewrites paths relative to the module `path` into `crate::` ones. Imports of items, which
+/// are not visible outside of their module, are dropped.
+fn absolute_use_tree(tree: UseTree, path: &[Ident], local_items: &LocalItems) -> Option<UseTree> {
+    let first = match &tree {
+        UseTree::Path(UsePath { ident, .. }) => ident,
+        UseTree::Name(name) => &name.ident,
+        UseTree::Rename(rename) => &rename.ident,
+        UseTree::Glob(_) => return Some(tree),
+        UseTree::Group(group) => {
+            return use_group(group, |item| absolute_use_tree(item, path, local_items))
+        }
+    };
+
+    if first == "self" || first == "super" {
+        let mut base = path;
+        let mut tree = tree;
+        while let UseTree::Path(UsePath {
+            ident, tree: rest, ..
+        }) = &tree
+        {
+            if ident == "super" {
+                base = &base[..base.len().saturating_sub(1)];
+            } else if ident != "self" {
+                break;
+            }
+            tree = (**rest).clone();
+        }
+        return resolve_in(tree, base, local_items);
+    }
+
+    let is_local = local_items
+        .get(&path_key(path))
+        .map(|items| items.iter().any(|(ident, _)| ident == first))
+        .unwrap_or(false);
+    if is_local {
+        resolve_in(tree, path, local_items)
+    } else {
+        Some(tree)
+    }
+}
+
+/// Makes `tree`, which is relative to the module `base`, absolute.
+fn resolve_in(tree: UseTree, base: &[Ident], local_items: &LocalItems) -> Option<UseTree> {
+    let first = match &tree {
+        UseTree::Path(UsePath { ident, .. }) => ident,
+        UseTree::Name(name) => &name.ident,
+        UseTree::Rename(rename) => &rename.ident,
+        UseTree::Glob(_) => return Some(prefixed(base, tree)),
+        UseTree::Group(group) => {
+            return use_group(group, |item| resolve_in(item, base, local_items));
+        }
+    };
+
+    // Imports and unknown items are private, unless they belong to the crate root
+    let visible = local_items
+        .get(&path_key(base))
+        .and_then(|items| items.iter().find(|(ident, _)| ident == first))
+        .map(|(_, visible)| *visible)
+        .unwrap_or_else(|| base.is_empty());
+    if visible {
+        Some(prefixed(base, tree))
+    } else {
+        None
+    }
+}
+
+fn use_group(group: &UseGroup, resolve: impl Fn(UseTree) -> Option<UseTree>) -> Option<UseTree> {
+    let items: syn::punctuated::Punctuated<UseTree, syn::Token![,]> =
+        group.items.iter().cloned().filter_map(resolve).collect();
+    if items.is_empty() {
+        None
+    } else {
+        Some(UseTree::Group(UseGroup {
+            brace_token: group.brace_token,
+            items,
+        }))
+    }
+}
+
+fn path_key(path: &[Ident]) -> Vec<String> {
+    path.iter().map(Ident::to_string).collect()
+}
+
+fn prefixed(path: &[Ident], tree: UseTree) -> UseTree {
+    std::iter::once(Ident::new("crate", Span::call_site()))
+        .chain(path.iter().cloned())
+        .rev()
+        .fold(tree, |tree, ident| {
+            UseTree::Path(UsePath {
+                ident,
+                colon2_token: Default::default(),
+                tree: Box::new(tree),
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::io::Write;
+    use tempfile::{tempdir, TempDir};
+
+    const LIB_RS: &str = r#"
+        use std::collections::HashMap;
+        mod private;
+        pub mod public {
+            use super::HashMap;
+            pub fn f(a: u8) {}
+            fn g(a: u8) {}
+            pub fn generic<T>(a: T) {}
+            pub struct S;
+            impl S {
+                pub fn m(&self, a: u8) {}
+                fn n(&self, a: u8) {}
+            }
+            impl Clone for S {
+                fn clone(&self) -> S { S }
+            }
+            mod hidden {
+                pub fn h(a: u8) {}
+                #[create_cargofuzz_harness(public::hidden)]
+                pub fn annotated_hidden(a: u8) {}
+            }
+            #[cfg(feature = "nested")]
+            pub mod nested {
+                use super::{S, hidden::h};
+                pub fn k(s: u8) {}
+            }
+        }
+        #[cfg(test)]
+        mod tests {
+            pub fn t(a: u8) {}
+        }
+        pub fn root(a: u8) {}
+        #[create_cargofuzz_harness]
+        pub fn annotated(a: u8) {}
+    "#;
+
+    const PRIVATE_RS: &str = r#"
+        use self::inner::X;
+        use self::hidden::Y;
+        pub(crate) mod inner {
+            pub struct X;
+        }
+        mod hidden {
+            pub struct Y;
+        }
+        pub fn p(x: u8) {}
+    "#;
+
+    fn crate_dir() -> TempDir {
+        let dir = tempdir().expect("Could not create tempdir fot test");
+        fs::create_dir(dir.path().join("src")).unwrap();
+        let mut lib_rs = fs::File::create(dir.path().join("src").join("lib.rs")).unwrap();
+        write!(lib_rs, "{}", LIB_RS).unwrap();
+        let mut private_rs = fs::File::create(dir.path().join("src").join("private.rs")).unwrap();
+        write!(private_rs, "{}", PRIVATE_RS).unwrap();
+        dir
+    }
+
+    fn summary(modules: &[ScannedModule]) -> Vec<(String, Vec<String>)> {
+        modules
+            .iter()
+            .map(|module| {
+                let mut items: Vec<String> = module
+                    .functions
+                    .iter()
+                    .map(|function| function.sig.ident.to_string())
+                    .collect();
+                for implementation in &module.impls {
+                    for item in &implementation.items {
+                        if let ImplItem::Method(method) = item {
+                            items.push(format!("S::{}", method.sig.ident));
+                        }
+                    }
+                }
+                (module.module_path().to_string(), items)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn scan_all() {
+        let dir = crate_dir();
+        let modules = scan_crate(dir.path(), &[], &[]).unwrap();
+        assert_eq!(
+            summary(&modules),
+            vec![
+                ("private".to_string(), vec!["p".to_string()]),
+                ("public :: nested".to_string(), vec!["k".to_string()]),
+                (
+                    "public".to_string(),
+                    vec!["f".to_string(), "S::m".to_string()]
+                ),
+                ("".to_string(), vec!["root".to_string()]),
+            ]
+        );
+
+        let uses = |module: &ScannedModule| -> Vec<String> {
+            module
+                .uses
+                .iter()
+                .map(|item_use| quote!(#item_use).to_string())
+                .collect()
+        };
+        assert_eq!(uses(&modules[0]), ["use crate :: private :: inner :: X ;"]);
+        assert_eq!(uses(&modules[1]), ["use { crate :: public :: S } ;"]);
+        assert_eq!(uses(&modules[2]), ["use crate :: HashMap ;"]);
+        assert_eq!(uses(&modules[3]), ["use std :: collections :: HashMap ;"]);
+
+        assert_eq!(modules[1].cfgs.len(), 1);
+        assert_eq!(modules[1].mirror_ident(), "__mod__public__nested");
+        assert_eq!(modules[3].mirror_ident(), "__mod");
+    }
+
+    #[test]
+    fn scan_harnessed_items() {
+        let dir = crate_dir();
+        let harnessed = scan_harnessed(dir.path()).unwrap();
+        let summary: Vec<(String, Option<String>)> = harnessed
+            .iter()
+            .map(|item| {
+                let path = &item.path;
+                (
+                    quote!(#(#path)::*).to_string(),
+                    item.hidden_by.as_ref().map(Ident::to_string),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("public :: hidden".to_string(), Some("hidden".to_string())),
+                ("".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn entry_points_invocation() {
+        let dir = crate_dir();
+        assert_eq!(has_entry_points(dir.path()), Ok(false));
+        fs::write(
+            dir.path().join("src").join("lib.rs"),
+            format!(
+                "{}\nauto_fuzz_test::create_cargofuzz_entry_points!();",
+                LIB_RS
+            ),
+        )
+        .unwrap();
+        assert_eq!(has_entry_points(dir.path()), Ok(true));
+    }
+
+    #[test]
+    fn find_module() {
+        let dir = crate_dir();
+        let items = module_items(dir.path(), &[format_ident!("private")]).unwrap();
+        assert_eq!(items.len(), 5);
+        let items = module_items(
+            dir.path(),
+            &[format_ident!("public"), format_ident!("nested")],
+        )
+        .unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(module_items(dir.path(), &[format_ident!("missing")]).is_err());
+    }
+
+    #[test]
+    fn scan_filtered() {
+        let dir = crate_dir();
+        let modules = scan_crate(
+            dir.path(),
+            &["public*".to_string()],
+            &["*nested".to_string()],
+        )
+        .unwrap();
+        assert_eq!(
+            summary(&modules),
+            vec![(
+                "public".to_string(),
+                vec!["f".to_string(), "S::m".to_string()]
+            )]
+        );
+
+        // Single items are matched by their paths
+        let modules = scan_crate(
+            dir.path(),
+            &["public::S::*".to_string(), "root".to_string()],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            summary(&modules),
+            vec![
+                ("public".to_string(), vec!["S::m".to_string()]),
+                ("".to_string(), vec!["root".to_string()]),
+            ]
+        );
+        let modules = scan_crate(
+            dir.path(),
+            &["public".to_string()],
+            &["public::S::m".to_string()],
+        )
+        .unwrap();
+        assert_eq!(
+            summary(&modules),
+            vec![("public".to_string(), vec!["f".to_string()])]
+        );
+    }
+}
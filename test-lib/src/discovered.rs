@@ -0,0 +1,29 @@
+use std::collections::BTreeSet;
+
+pub fn count_unique(values: Vec<u8>) -> usize {
+    values.iter().collect::<BTreeSet<_>>().len()
+}
+
+#[cfg_attr(fuzzing, derive(arbitrary::Arbitrary))]
+#[derive(Debug)]
+pub struct Counter {
+    count: u32,
+}
+
+impl Counter {
+    pub fn increment(&mut self, by: u32) {
+        self.count = self.count.saturating_add(by);
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+pub mod nested {
+    use super::Counter;
+
+    pub fn total(counters: Vec<Counter>) -> u32 {
+        counters.iter().map(Counter::count).sum()
+    }
+}
@@ -1,21 +1,62 @@
 #[macro_use]
 extern crate auto_fuzz_test;
-#[macro_use]
-extern crate arbitrary;
 
-#[create_cargofuzz_harness]
+/// ```
+/// assert_eq!(test_lib::bool_to_num("four".to_string()), 4);
+/// ```
+#[create_cargofuzz_harness(property_tests = true, seeds = true)]
 pub fn bool_to_num(string: String) -> usize {
     string.len()
 }
 
+/// ```
+/// assert_eq!(test_lib::checksum(b"abc"), 38);
+/// ```
+#[create_cargofuzz_harness(property_tests = true, seeds = true)]
+pub fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum: u8, byte| sum.wrapping_add(*byte))
+}
+
+#[create_cargofuzz_harness(property_tests = true)]
+pub async fn word_count(text: String) -> usize {
+    std::future::ready(()).await;
+    text.split_whitespace().count()
+}
+
+/// Valid UTF-8 is the safety precondition of `first_word`.
+pub fn is_utf8(bytes: &[u8]) -> bool {
+    std::str::from_utf8(bytes).is_ok()
+}
+
+/// # Safety
+/// `bytes` must be valid UTF-8.
+#[create_cargofuzz_harness(unsafe_precondition = is_utf8, property_tests = true)]
+pub unsafe fn first_word(bytes: &[u8]) -> &str {
+    let text = std::str::from_utf8_unchecked(bytes);
+    text.split_whitespace().next().unwrap_or_default()
+}
+
+pub mod discovered;
 pub mod module;
+mod private;
 
-#[derive(Arbitrary, Debug)]
+create_cargofuzz_crate_harness!(include = "discovered*");
+
+pub fn digit_value(digit: char) -> u32 {
+    private::parse_digit(digit, 10)
+}
+
+pub fn hex_value(digits: &str) -> Option<u32> {
+    private::parse_number(digits, 16)
+}
+
+#[cfg_attr(fuzzing, derive(arbitrary::Arbitrary))]
+#[derive(Debug)]
 pub struct TestStruct {
     a: u64,
     b: u64,
 }
-#[create_cargofuzz_impl_harness]
+#[create_cargofuzz_impl_harness(seeds = true)]
 impl TestStruct {
     pub fn new(a: u64, b: u64) -> TestStruct {
         TestStruct { a, b }
@@ -33,3 +74,15 @@ impl TestStruct {
         self.a *= self.b;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multiply() {
+        let mut test_struct = TestStruct::new(6, 7);
+        test_struct.multiply();
+        assert_eq!(test_struct.get_a(), 42);
+    }
+}
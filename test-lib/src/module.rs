@@ -15,3 +15,92 @@ pub fn maybe_checked_mul_borrowed(a: &mut u64, b: u64, crash_on_overflow: bool)
         *a = a.overflowing_mul(b).0;
     }
 }
+
+/// Does not implement `Arbitrary`, so fuzzed functions get it from a custom generator.
+#[derive(Debug)]
+pub struct Limits {
+    pub max: u64,
+}
+
+#[cfg(fuzzing)]
+fn small_limits(u: &mut arbitrary::Unstructured) -> arbitrary::Result<Limits> {
+    Ok(Limits {
+        max: u.int_in_range(0..=1000)?,
+    })
+}
+
+#[create_cargofuzz_harness(module)]
+pub fn clamp(a: u64, #[fuzz(with = small_limits)] limits: &Limits) -> u64 {
+    a.min(limits.max)
+}
+
+#[create_cargofuzz_harness(module)]
+pub fn subnet(addr: std::net::IpAddr, prefix: std::num::NonZeroU8) -> bool {
+    match addr {
+        std::net::IpAddr::V4(_) => prefix.get() <= 32,
+        std::net::IpAddr::V6(_) => prefix.get() <= 128,
+    }
+}
+
+#[create_cargofuzz_harness(module, property_tests = true)]
+pub fn json_depth(#[fuzz(grammar = "json")] document: &str) -> usize {
+    let mut depth: usize = 0;
+    let mut max_depth = 0;
+    let mut in_string = false;
+    let mut chars = document.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => in_string = !in_string,
+            '\\' if in_string => {
+                chars.next();
+            }
+            '[' | '{' if !in_string => depth += 1,
+            ']' | '}' if !in_string => depth -= 1,
+            _ => {}
+        }
+        max_depth = max_depth.max(depth);
+    }
+    max_depth
+}
+
+#[create_cargofuzz_harness(module, property_tests = true)]
+pub fn count_operators(#[fuzz(grammar_file = "grammars/arithmetic.gram")] expression: String) -> usize {
+    expression.matches(|c| c == '+' || c == '-' || c == '*').count()
+}
+
+thread_local! {
+    static SEPARATOR: std::cell::Cell<char> = const { std::cell::Cell::new(',') };
+}
+
+#[cfg(any(fuzzing, test))]
+fn separator_setup(u: &mut arbitrary::Unstructured) -> arbitrary::Result<()> {
+    let value = u.arbitrary()?;
+    SEPARATOR.with(|separator| separator.set(value));
+    Ok(())
+}
+
+#[create_cargofuzz_harness(module, setup = "separator_setup", property_tests = true)]
+pub fn separator_width() -> usize {
+    SEPARATOR.with(|separator| separator.get().len_utf8())
+}
+
+#[create_cargofuzz_harness(module, property_tests = true)]
+pub fn retain_dedup(
+    mut values: Vec<u8>,
+    keep: impl Fn(&u8) -> bool,
+    same: &dyn Fn(&u8, &u8) -> bool,
+) -> Vec<u8> {
+    values.retain(|value| keep(value));
+    values.dedup_by(|a, b| same(a, b));
+    values
+}
+
+#[create_cargofuzz_harness(module, property_tests = true)]
+pub fn largest(values: Vec<f64>, mut compare: impl FnMut(&f64, &f64) -> std::cmp::Ordering) -> f64 {
+    values
+        .into_iter()
+        .fold(f64::NAN, |largest, value| match compare(&largest, &value) {
+            std::cmp::Ordering::Less => value,
+            _ => largest,
+        })
+}
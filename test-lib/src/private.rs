@@ -0,0 +1,13 @@
+#[create_cargofuzz_harness(private)]
+pub(crate) fn parse_digit(digit: char, radix: u32) -> u32 {
+    digit.to_digit(radix).expect("Not a digit")
+}
+
+#[create_cargofuzz_harness(private)]
+pub(crate) fn parse_number(
+    #[fuzz(max_len = 16)] digits: &str,
+    #[fuzz(range = 2..=36)] radix: u32,
+) -> Option<u32> {
+    // `from_str_radix` panics on radixes outside of `2..=36`
+    u32::from_str_radix(digits, radix).ok()
+}